@@ -3,10 +3,12 @@ use figment::Figment;
 use serde::*;
 use std::collections::HashMap;
 use std::error::Error;
+use std::fmt::{Display, Formatter};
 use std::time::Duration;
 use validator::Validate;
 
 use crate::publisher::*;
+use crate::schedule::ScheduleConfig;
 use crate::sensor::*;
 use crate::APP_NAME;
 
@@ -16,24 +18,48 @@ pub struct Config {
     pub debug: bool,
     pub interval: ConfigInterval,
     pub device: ConfigDevice,
-    pub sensors: HashMap<String, ConfigSensor>,
+    pub sensors: HashMap<String, ConfigSensorEntry>,
     pub publishers: HashMap<String, ConfigPublisher>,
 }
 
 impl Config {
-    pub fn load() -> Result<Self, Box<dyn Error>> {
+    pub fn load() -> Result<Self, ConfigError> {
         let config: Config =
             Figment::from(Serialized::defaults(Config::default()))
                 .merge(Yaml::file("config.yml"))
-                .extract()?;
+                .extract()
+                .map_err(|e| ConfigError::Load(e.to_string()))?;
 
-        match config.validate() {
-            Ok(_) => Ok(config),
-            Err(e) => Err(Box::new(e)),
+        if let Err(e) = config.validate() {
+            return Err(ConfigError::Invalid(e.to_string()));
+        }
+        for entry in config.sensors.values() {
+            entry.options.schedule.cron()?;
+        }
+        Ok(config)
+    }
+}
+
+/// Why the configuration can't be used.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    /// File unreadable or not matching the expected structure
+    Load(String),
+    /// A value out of its bounds
+    Invalid(String),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Load(msg) => write!(f, "Can't load config: {msg}"),
+            ConfigError::Invalid(msg) => write!(f, "Invalid config: {msg}"),
         }
     }
 }
 
+impl Error for ConfigError {}
+
 #[derive(Deserialize, Serialize, Debug)]
 pub struct ConfigDevice {
     pub name: String,
@@ -68,6 +94,83 @@ impl From<ConfigInterval> for Duration {
     }
 }
 
+/// A sensor and the options common to all of them, side by side:
+/// `{ am2320: { bus: 1 }, derived: [dew_point] }`
+#[derive(Serialize, Debug)]
+pub struct ConfigSensorEntry {
+    #[serde(flatten)]
+    pub sensor: ConfigSensor,
+    #[serde(flatten)]
+    pub options: ConfigSensorOptions,
+}
+
+impl ConfigSensorEntry {
+    /// Ids the sensors of the entry publish to, as far as they can be known
+    /// before creating them
+    pub fn sensor_ids(&self, entry_id: &str) -> Vec<String> {
+        let sensor_ids = <dyn Sensor>::sensor_ids(entry_id, &self.sensor);
+
+        let oversampling = Oversampling::new(&self.options.oversampling);
+        sensor_ids
+            .iter()
+            .flat_map(|sensor_id| oversampling.sensor_ids(sensor_id))
+            .collect()
+    }
+}
+
+// serde `flatten` can't find enum variants by their alias, so the options
+// are taken out of the mapping and what remains is the sensor.
+impl<'de> Deserialize<'de> for ConfigSensorEntry {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        use serde::de::Error;
+        use serde_json::Value;
+
+        let mut mapping = serde_json::Map::deserialize(deserializer)?;
+
+        let options =
+            ConfigSensorOptions::deserialize(Value::Object(mapping.clone()))
+                .map_err(D::Error::custom)?;
+        let keys = serde_json::to_value(ConfigSensorOptions::default())
+            .map_err(D::Error::custom)?;
+        if let Value::Object(keys) = keys {
+            keys.keys().for_each(|key| {
+                mapping.remove(key);
+            });
+        }
+
+        let sensor = ConfigSensor::deserialize(Value::Object(mapping))
+            .map_err(D::Error::custom)?;
+
+        Ok(Self { sensor, options })
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(default)]
+pub struct ConfigSensorOptions {
+    #[serde(flatten)]
+    pub schedule: ScheduleConfig,
+    /// Corrections applied to the measures before anything else
+    pub calibration: HashMap<SensorMeasureType, CalibrationConfig>,
+    /// Chains of filters applied to the calibrated measures
+    pub filters: HashMap<SensorMeasureType, Vec<FilterConfig>>,
+    /// Quantities computed from the temperature and humidity
+    pub derived: Vec<SensorMeasureType>,
+    pub oversampling: OversamplingConfig,
+    /// Publish only when a measure moved at least this much
+    pub min_change: HashMap<SensorMeasureType, MinChange>,
+    /// Publish anyway when nothing was published for this long
+    #[serde(with = "humantime_serde")]
+    pub max_silence: Option<Duration>,
+    /// A read still running after this is abandoned, 30s by default
+    #[serde(with = "humantime_serde")]
+    pub read_timeout: Option<Duration>,
+    /// Retries of failed reads and probes of an unavailable sensor
+    pub retry: RetryConfig,
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "snake_case")]
 pub enum ConfigSensor {
@@ -75,8 +178,22 @@ pub enum ConfigSensor {
     Faker(FakerConfig),
     #[serde(alias = "am2320")]
     AM2320(AM2320Config),
+    #[serde(alias = "bme280", alias = "bmp280")]
+    Bme280(Bme280Config),
+    #[serde(alias = "dht22")]
+    Dht22(Dht22Config),
     #[serde(alias = "ds18b20")]
     Ds18b20(Ds18b20Config),
+    #[serde(alias = "sht")]
+    Sht(ShtConfig),
+    #[serde(alias = "thermal_zone")]
+    ThermalZone(ThermalZoneConfig),
+    #[serde(alias = "sysfs", alias = "hwmon", alias = "iio")]
+    Sysfs(SysfsConfig),
+    #[serde(alias = "system")]
+    System(SystemConfig),
+    #[serde(alias = "exec")]
+    Exec(ExecConfig),
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -85,3 +202,143 @@ pub enum ConfigPublisher {
     Mqtt(MqttPublisherConfig),
     Stdout(StdoutPublisherConfig),
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    use figment::providers::{Format, Yaml};
+    use figment::Figment;
+
+    use crate::{
+        Aggregate, FilterConfig, MinChange, OversamplingConfig, RetryConfig,
+        SensorMeasureType,
+    };
+
+    use super::{ConfigSensor, ConfigSensorEntry};
+
+    #[test]
+    fn config_sensor_entry_ids() {
+        let sensors: HashMap<String, ConfigSensorEntry> = Figment::new()
+            .merge(Yaml::string(
+                "
+                hall:
+                  bme280: {}
+                  oversampling: { samples: 4, min_max: true }
+                fermenters:
+                  ds18b20: { discover: true, names: { 28-0a0b: top, 28-0c0d: bottom } }
+                host:
+                  system: { mount_points: [/] }
+                ",
+            ))
+            .extract()
+            .unwrap();
+
+        assert_eq!(
+            sensors["hall"].sensor_ids("hall"),
+            vec!["hall", "hall-min", "hall-max"]
+        );
+        // named probes and the unnamed ones found on the bus
+        assert_eq!(
+            sensors["fermenters"].sensor_ids("fermenters"),
+            vec!["top", "bottom"]
+        );
+        assert_eq!(
+            sensors["host"].sensor_ids("host"),
+            vec!["host", "host-disk-root"]
+        );
+    }
+
+    #[test]
+    fn config_sensor_entry() {
+        let sensors: HashMap<String, ConfigSensorEntry> = Figment::new()
+            .merge(Yaml::string(
+                "
+                hall: { bmp280: { address: 0x77 } }
+                bathroom:
+                  am2320: { bus: 1 }
+                  derived: [dew_point, humidex]
+                  calibration:
+                    temperature: { offset: -1.2 }
+                    humidity: { points: [[20, 22.5], [80, 78]] }
+                  filters:
+                    temperature: [rate_of_change: 5, { z_score: { window: 10, threshold: 3 } }, median: 3]
+                  oversampling: { samples: 8, min_max: true }
+                  min_change: { temperature: 0.2, humidity: 5% }
+                  max_silence: 30m
+                  interval: 2m
+                  align: true
+                  read_timeout: 5s
+                  retry: { retries: 3, probe_interval: 5m }
+                ",
+            ))
+            .extract()
+            .unwrap();
+
+        let hall = &sensors["hall"];
+        assert!(
+            matches!(&hall.sensor, ConfigSensor::Bme280(c) if c.address == 0x77)
+        );
+        assert!(hall.options.derived.is_empty());
+        assert!(hall.options.calibration.is_empty());
+        assert_eq!(hall.options.oversampling, OversamplingConfig::default());
+
+        let bathroom = &sensors["bathroom"];
+        assert!(matches!(bathroom.sensor, ConfigSensor::AM2320(_)));
+        assert_eq!(
+            bathroom.options.derived,
+            vec![SensorMeasureType::DewPoint, SensorMeasureType::Humidex]
+        );
+        let calibration = &bathroom.options.calibration;
+        assert_eq!(calibration[&SensorMeasureType::Temperature].offset, -1.2);
+        assert_eq!(
+            calibration[&SensorMeasureType::Humidity].points,
+            vec![(20.0, 22.5), (80.0, 78.0)]
+        );
+        assert_eq!(
+            bathroom.options.filters[&SensorMeasureType::Temperature],
+            vec![
+                FilterConfig::RateOfChange(5.0),
+                FilterConfig::ZScore {
+                    window: 10,
+                    threshold: 3.0
+                },
+                FilterConfig::Median(3)
+            ]
+        );
+        assert_eq!(
+            bathroom.options.oversampling,
+            OversamplingConfig {
+                samples: 8,
+                aggregate: Aggregate::Mean,
+                min_max: true
+            }
+        );
+        assert_eq!(
+            bathroom.options.min_change,
+            HashMap::from([
+                (SensorMeasureType::Temperature, MinChange::Absolute(0.2)),
+                (SensorMeasureType::Humidity, MinChange::Percent(5.0))
+            ])
+        );
+        assert_eq!(
+            bathroom.options.schedule.interval,
+            Some(Duration::from_secs(120))
+        );
+        assert!(bathroom.options.schedule.align);
+        assert_eq!(
+            bathroom.options.max_silence,
+            Some(Duration::from_secs(1800))
+        );
+        assert_eq!(bathroom.options.read_timeout, Some(Duration::from_secs(5)));
+        assert_eq!(
+            bathroom.options.retry,
+            RetryConfig {
+                retries: 3,
+                probe_interval: Duration::from_secs(300),
+                ..Default::default()
+            }
+        );
+    }
+}
@@ -1,9 +1,11 @@
 mod config;
 mod publisher;
+mod schedule;
 mod sensor;
 
-pub use config::Config;
+pub use config::{Config, ConfigError, ConfigSensorEntry};
 pub use publisher::*;
+pub use schedule::{Schedule, ScheduleConfig};
 pub use sensor::*;
 
 pub const APP_NAME: &str = env!("CARGO_PKG_NAME");
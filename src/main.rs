@@ -1,74 +1,335 @@
 use log::*;
 use sensors_pub::*;
 use std::collections::HashMap;
-use std::env;
 use std::error::Error;
-use tokio::time::sleep;
+use std::sync::{Arc, Mutex, TryLockError};
+use std::time::{Duration, Instant, SystemTime};
+use std::{env, process};
+use tokio::task::{spawn_blocking, JoinSet};
+use tokio::time::{sleep, timeout};
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn Error>> {
-    let config = Config::load()?;
+type Publishers = HashMap<String, Box<dyn Publisher>>;
 
-    if env::var("RUST_LOG").is_err() {
-        env::set_var("RUST_LOG", if config.debug { "debug" } else { "info" });
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+const CREATE_RETRY_MIN: Duration = Duration::from_secs(10);
+const CREATE_RETRY_MAX: Duration = Duration::from_secs(600);
+
+/// A sensor with its own schedule and the state kept between its reads.
+struct ScheduledSensor {
+    sensor_id: String,
+    sensor: Arc<Mutex<Box<dyn Sensor>>>,
+    measure_types: Vec<SensorMeasureType>,
+    read_timeout: Duration,
+    retry: Retry,
+    available: Option<bool>,
+    schedule: Schedule,
+    oversampling: Oversampling,
+    dead_band: DeadBand,
+}
+
+impl ScheduledSensor {
+    fn new(
+        sensor_id: String,
+        sensor: Box<dyn Sensor>,
+        entry: &ConfigSensorEntry,
+        interval: Duration,
+    ) -> Result<Self, ConfigError> {
+        let options = &entry.options;
+        let oversampling = Oversampling::new(&options.oversampling);
+        let interval = options.schedule.interval.unwrap_or(interval);
+        let schedule =
+            Schedule::new(&options.schedule, oversampling.period(interval))?;
+
+        Ok(Self {
+            sensor_id,
+            measure_types: sensor.measure_types().clone(),
+            sensor: Arc::new(Mutex::new(sensor)),
+            read_timeout: options.read_timeout.unwrap_or(READ_TIMEOUT),
+            retry: Retry::new(&options.retry),
+            available: None,
+            schedule,
+            oversampling,
+            dead_band: DeadBand::new(&options.min_change, options.max_silence),
+        })
     }
-    env_logger::builder().format_timestamp_millis().init();
 
-    debug!("\n{:#?}", config);
+    fn sensor_ids(&self) -> Vec<String> {
+        self.oversampling.sensor_ids(&self.sensor_id)
+    }
+
+    /// Read and publish forever, a slow read only delays this sensor.
+    async fn run(mut self, publishers: Arc<Publishers>) {
+        let mut last = None;
+
+        loop {
+            let next = match self.retry.available() {
+                // on the wall clock, so aligned reads don't drift
+                true => self.schedule.next(last, SystemTime::now()),
+                false => SystemTime::now() + self.retry.probe_interval(),
+            };
+            let delay =
+                next.duration_since(SystemTime::now()).unwrap_or_default();
+            sleep(delay + self.schedule.jitter()).await;
+            last = Some(next);
+
+            let measure = self.read().await;
+            let available = self.retry.available();
+            // online only once a read succeeded
+            if self.available != Some(available)
+                && (measure.is_some() || !available)
+            {
+                self.available = Some(available);
+                publish_availability(
+                    &publishers,
+                    &self.sensor_ids(),
+                    available,
+                )
+                .await;
+            }
+
+            let Some(oversampled) = self.oversampling.push(measure) else {
+                continue;
+            };
+            if !self.dead_band.publish(&oversampled.measure, Instant::now()) {
+                debug!("Nothing to publish ({}), no change", self.sensor_id);
+                continue;
+            }
 
-    let mut sensors = config
-        .sensors
-        .iter()
-        .map(|(k, s)| (k, <dyn Sensor>::new(s)))
-        .collect::<HashMap<_, _>>();
-    info!("Sensors count: {}", sensors.len());
-
-    let publishers = config
-        .publishers
-        .iter()
-        .map(|(k, v)| (k, <dyn Publisher>::new(&config, v).unwrap()))
-        .collect::<HashMap<_, _>>();
-    info!("Publishers count: {}", sensors.len());
-
-    for (sensor_id, sensor) in &sensors {
-        for measure_type in sensor.measure_types() {
-            for (publisher_id, publisher) in &publishers {
-                if let Err(err) = publisher
-                    .declare_sensor_measure_type(measure_type, sensor_id)
-                    .await
-                {
-                    error!(
-                        "Error add sensor ({1} -> {0}). {2}",
-                        publisher_id, sensor_id, err
+            let mut measures =
+                vec![(self.sensor_id.clone(), oversampled.measure)];
+            if let Some((min, max)) = oversampled.min_max {
+                measures.push((format!("{}-min", self.sensor_id), min));
+                measures.push((format!("{}-max", self.sensor_id), max));
+            }
+
+            for (sensor_id, measure) in &measures {
+                if measure.is_empty() {
+                    debug!(
+                        "Nothing to publish ({sensor_id}), all filtered out"
                     );
+                    continue;
+                }
+
+                for (publisher_id, publisher) in publishers.iter() {
+                    if let Err(err) =
+                        publisher.publish(measure, sensor_id).await
+                    {
+                        error!(
+                            "Error publishing the measurement ({1} -> {0}). {2}",
+                            publisher_id, sensor_id, err
+                        );
+                    }
                 }
             }
         }
     }
 
-    info!("Start measure loop");
-    loop {
-        for (sensor_id, sensor) in &mut sensors {
-            let measure = match sensor.measure() {
-                Ok(m) => m,
-                Err(err) => {
-                    error!(
-                        "Error reading sensor measurement ({}). {}",
-                        sensor_id, err
-                    );
-                    continue;
-                }
+    /// A read retried right away while it fails and the policy allows it
+    async fn read(&mut self) -> Option<Measure> {
+        let start = Instant::now();
+        let mut retries = 0;
+        let result = loop {
+            let err = match self.read_once().await {
+                Ok(measure) => break Ok(measure),
+                Err(err) => err,
             };
+            let Some(delay) = self.retry.delay(retries, &err) else {
+                break Err(err);
+            };
+            retries += 1;
+            warn!(
+                "Read failed ({}), retry {} in {:?}. {}",
+                self.sensor_id, retries, delay, err
+            );
+            sleep(delay).await;
+        };
 
-            for (publisher_id, publisher) in &publishers {
-                if let Err(err) = publisher.publish(&measure, sensor_id).await {
-                    error!(
-                        "Error publishing the measurement ({1} -> {0}). {2}",
-                        publisher_id, sensor_id, err
-                    );
+        match self.retry.read(result.as_ref().err()) {
+            Some(true) => info!("Sensor available again ({})", self.sensor_id),
+            Some(false) => warn!(
+                "Sensor unavailable ({}), probed every {:?}",
+                self.sensor_id,
+                self.retry.probe_interval()
+            ),
+            None => (),
+        }
+
+        match result {
+            Ok(mut measure) => {
+                measure.metadata.time = Some(SystemTime::now());
+                measure.metadata.read_duration = Some(start.elapsed());
+                measure.metadata.retries += retries;
+                Some(measure)
+            }
+            Err(err) => {
+                error!(
+                    "Error reading sensor measurement ({}). {}",
+                    self.sensor_id, err
+                );
+                None
+            }
+        }
+    }
+
+    async fn read_once(&self) -> Result<Measure, SensorError> {
+        let sensor = self.sensor.clone();
+        // reads block, off the runtime so a hung bus only stalls this sensor
+        let read = spawn_blocking(move || match sensor.try_lock() {
+            Ok(mut sensor) => sensor.measure(),
+            Err(TryLockError::Poisoned(sensor)) => {
+                sensor.into_inner().measure()
+            }
+            Err(TryLockError::WouldBlock) => {
+                Err(SensorError::Timeout("Previous read still running".into()))
+            }
+        });
+
+        match timeout(self.read_timeout, read).await {
+            Ok(Ok(result)) => result,
+            // the read panicked
+            Ok(Err(err)) => Err(SensorError::Io(err.to_string())),
+            Err(_) => Err(SensorError::Timeout(format!(
+                "Read after {:?}",
+                self.read_timeout
+            ))),
+        }
+    }
+}
+
+async fn publish_availability(
+    publishers: &Publishers,
+    sensor_ids: &[String],
+    available: bool,
+) {
+    for sensor_id in sensor_ids {
+        for (publisher_id, publisher) in publishers {
+            if let Err(err) =
+                publisher.sensor_availability(sensor_id, available).await
+            {
+                error!(
+                    "Error publishing the availability ({1} -> {0}). {2}",
+                    publisher_id, sensor_id, err
+                );
+            }
+        }
+    }
+}
+
+/// Create the sensors of a config entry and run them. Retried with backoff
+/// while it fails, an unplugged probe doesn't stop the other sensors.
+async fn start_sensors(
+    entry_id: String,
+    config: Arc<Config>,
+    publishers: Arc<Publishers>,
+) {
+    let entry = &config.sensors[&entry_id];
+    // replaces an `online` retained from a previous run until a read succeeds
+    publish_availability(&publishers, &entry.sensor_ids(&entry_id), false)
+        .await;
+
+    let mut retry = CREATE_RETRY_MIN;
+    let sensors = loop {
+        let created = {
+            let (entry_id, config) = (entry_id.clone(), config.clone());
+            // construction talks to the devices too
+            spawn_blocking(move || {
+                <dyn Sensor>::new_all(&entry_id, &config.sensors[&entry_id])
+            })
+            .await
+            .unwrap_or_else(|err| Err(SensorError::Io(err.to_string())))
+        };
+        match created {
+            Ok(sensors) => break sensors,
+            // won't be better next time
+            Err(err @ SensorError::Config(_)) => {
+                error!("Error creating sensor ({entry_id}), disabled. {err}");
+                return;
+            }
+            Err(err) => error!(
+                "Error creating sensor ({entry_id}), retry in {retry:?}. {err}"
+            ),
+        }
+        sleep(retry).await;
+        retry = (retry * 2).min(CREATE_RETRY_MAX);
+    };
+
+    let mut tasks = JoinSet::new();
+    for (sensor_id, sensor) in sensors {
+        let scheduled = match ScheduledSensor::new(
+            sensor_id.clone(),
+            sensor,
+            entry,
+            config.interval.into(),
+        ) {
+            Ok(scheduled) => scheduled,
+            Err(err) => {
+                error!("Error creating sensor ({sensor_id}), disabled. {err}");
+                continue;
+            }
+        };
+
+        for sensor_id in &scheduled.sensor_ids() {
+            for measure_type in &scheduled.measure_types {
+                for (publisher_id, publisher) in publishers.iter() {
+                    if let Err(err) = publisher
+                        .declare_sensor_measure_type(measure_type, sensor_id)
+                        .await
+                    {
+                        error!(
+                            "Error add sensor ({1} -> {0}). {2}",
+                            publisher_id, sensor_id, err
+                        );
+                    }
                 }
             }
         }
-        sleep(config.interval.into()).await;
+
+        info!("Start measure loop ({sensor_id})");
+        tasks.spawn(scheduled.run(publishers.clone()));
+    }
+    while tasks.join_next().await.is_some() {}
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let config = Config::load()?;
+
+    if env::var("RUST_LOG").is_err() {
+        env::set_var("RUST_LOG", if config.debug { "debug" } else { "info" });
+    }
+    env_logger::builder().format_timestamp_millis().init();
+
+    debug!("\n{:#?}", config);
+
+    let mut publishers = Publishers::new();
+    for (publisher_id, publisher) in &config.publishers {
+        match <dyn Publisher>::new(&config, publisher) {
+            Ok(publisher) => {
+                publishers.insert(publisher_id.clone(), publisher);
+            }
+            Err(err) => {
+                error!("Error creating publisher ({publisher_id}). {err}")
+            }
+        }
     }
+    info!("Publishers count: {}", publishers.len());
+    info!("Sensors count: {}", config.sensors.len());
+
+    let config = Arc::new(config);
+    let publishers = Arc::new(publishers);
+    let mut tasks = JoinSet::new();
+    for entry_id in config.sensors.keys() {
+        tasks.spawn(start_sensors(
+            entry_id.clone(),
+            config.clone(),
+            publishers.clone(),
+        ));
+    }
+    while let Some(result) = tasks.join_next().await {
+        result?;
+    }
+
+    // every sensor disabled, nothing will ever be published
+    error!("No sensor left to read");
+    process::exit(1)
 }
@@ -0,0 +1,45 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+/// Why a measure couldn't be published: the broker can come back, a payload
+/// that can't be serialized won't.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PublisherError {
+    /// Broker unreachable or the client queue closed
+    Connection(String),
+    /// Payload couldn't be serialized
+    Serialization(String),
+}
+
+impl Display for PublisherError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PublisherError::Connection(msg) => {
+                write!(f, "Connection error: {msg}")
+            }
+            PublisherError::Serialization(msg) => {
+                write!(f, "Serialization error: {msg}")
+            }
+        }
+    }
+}
+
+impl Error for PublisherError {}
+
+impl From<rumqttc::ClientError> for PublisherError {
+    fn from(err: rumqttc::ClientError) -> Self {
+        PublisherError::Connection(err.to_string())
+    }
+}
+
+impl From<serde_json::Error> for PublisherError {
+    fn from(err: serde_json::Error) -> Self {
+        PublisherError::Serialization(err.to_string())
+    }
+}
+
+impl From<serde_yaml::Error> for PublisherError {
+    fn from(err: serde_yaml::Error) -> Self {
+        PublisherError::Serialization(err.to_string())
+    }
+}
@@ -1,41 +1,55 @@
+mod error;
 mod mqtt;
+mod payload;
 mod stdout_publisher;
-
-use std::error::Error;
+mod units;
 
 use async_trait::async_trait;
+pub use error::PublisherError;
 pub use mqtt::mqtt_publisher::{MqttPublisher, MqttPublisherConfig};
+pub use payload::{Payload, PayloadConfig, PayloadTimestamp};
 pub use stdout_publisher::{StdoutPublisher, StdoutPublisherConfig};
+pub use units::{PressureUnit, TemperatureUnit, Units};
 
 use crate::config::ConfigPublisher;
 use crate::sensor::Measure;
 use crate::{Config, SensorMeasureType};
 
 #[async_trait]
-pub trait Publisher {
+pub trait Publisher: Send + Sync {
     async fn publish<'a>(
         &self,
         measure: &Measure,
         sensor_id: &'a str,
-    ) -> Result<(), Box<dyn Error>>;
+    ) -> Result<(), PublisherError>;
 
     async fn declare_sensor_measure_type<'a>(
         &self,
         _measure_type: &SensorMeasureType,
         _sensor_id: &'a str,
-    ) -> Result<(), Box<dyn Error>>;
+    ) -> Result<(), PublisherError>;
+
+    /// Whether the reads of a sensor succeed, so a stale value isn't shown
+    /// as fresh
+    async fn sensor_availability<'a>(
+        &self,
+        _sensor_id: &'a str,
+        _available: bool,
+    ) -> Result<(), PublisherError> {
+        Ok(())
+    }
 }
 
 impl dyn Publisher {
     pub fn new(
         config: &Config,
         publisher: &ConfigPublisher,
-    ) -> Result<Box<dyn Publisher>, Box<dyn Error>> {
+    ) -> Result<Box<dyn Publisher>, PublisherError> {
         match publisher {
             ConfigPublisher::Mqtt(c) => {
                 Ok(Box::new(MqttPublisher::create(&config.device, c)))
             }
-            ConfigPublisher::Stdout(_) => Ok(Box::new(StdoutPublisher::new())),
+            ConfigPublisher::Stdout(c) => Ok(Box::new(StdoutPublisher::new(c))),
         }
     }
 }
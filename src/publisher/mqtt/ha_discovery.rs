@@ -2,6 +2,7 @@ use serde::Serialize;
 
 use crate::config::ConfigDevice;
 use crate::publisher::mqtt::MQTT_STATE_TOPIC_BASE;
+use crate::publisher::Units;
 use crate::sensor::SensorMeasureType;
 
 fn secure_mqtt_topic_name(string: &str) -> String {
@@ -38,17 +39,48 @@ impl HADevice {
             secure_mqtt_topic_name(sensor_name),
         )
     }
+
+    pub fn get_mqtt_availability_topic(&self) -> String {
+        format!(
+            "{}/{}/availability",
+            MQTT_STATE_TOPIC_BASE,
+            secure_mqtt_topic_name(&self.name),
+        )
+    }
+
+    pub fn get_mqtt_sensor_availability_topic(
+        &self,
+        sensor_name: &str,
+    ) -> String {
+        format!(
+            "{}/{}/{}/availability",
+            MQTT_STATE_TOPIC_BASE,
+            secure_mqtt_topic_name(&self.name),
+            secure_mqtt_topic_name(sensor_name),
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Default, Clone, PartialEq, Eq)]
+pub struct HAAvailability {
+    topic: String,
 }
 
 #[derive(Debug, Serialize, Default, PartialEq, Eq)]
 pub struct HASensor {
     name: String,
-    device_class: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    device_class: Option<&'static str>,
     state_class: &'static str,
-    unit_of_measurement: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    unit_of_measurement: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    entity_category: Option<&'static str>,
     state_topic: String,
     unique_id: String,
     value_template: String,
+    availability: Vec<HAAvailability>,
+    availability_mode: &'static str,
     device: HADevice,
 }
 
@@ -57,64 +89,42 @@ impl HASensor {
         measure: &SensorMeasureType,
         device: HADevice,
         sensor_id: &str,
+        units: &Units,
     ) -> Self {
-        let name = measure.name();
-        let device_class = measure.device_class();
-        let state_class = measure.state_class();
-        let unit_of_measurement = measure.unit_of_measurement();
+        let name = measure.quantity().name.into();
+        let quantity = measure.quantity();
+        let device_class = quantity.device_class;
+        let state_class = quantity.state_class;
+        let unit_of_measurement = units.unit(measure);
+        let entity_category = quantity.diagnostic.then_some("diagnostic");
         let state_topic = device.get_mqtt_state_topic(sensor_id);
-        let unique_id =
-            format!("{}_{}", sensor_id, measure.name().to_lowercase());
-        let value_template =
-            format!("{{{{ value_json.{} }}}}", measure.name().to_lowercase());
+        let unique_id = format!("{}_{}", sensor_id, measure.key());
+        let value_template = format!("{{{{ value_json.{} }}}}", measure.key());
+        let availability = vec![
+            HAAvailability {
+                topic: device.get_mqtt_availability_topic(),
+            },
+            HAAvailability {
+                topic: device.get_mqtt_sensor_availability_topic(sensor_id),
+            },
+        ];
 
         Self {
             name,
             device_class,
             state_class,
             unit_of_measurement,
+            entity_category,
             state_topic,
             unique_id,
             value_template,
+            availability,
+            availability_mode: "all",
             device,
         }
     }
 }
 
-trait HASensorInfo {
-    fn name(&self) -> String;
-    fn device_class(&self) -> &'static str;
-    fn state_class(&self) -> &'static str;
-    fn unit_of_measurement(&self) -> &'static str;
-}
-
-impl HASensorInfo for SensorMeasureType {
-    fn name(&self) -> String {
-        self.to_string()
-    }
-
-    fn device_class(&self) -> &'static str {
-        match self {
-            SensorMeasureType::Temperature => "temperature",
-            SensorMeasureType::Humidity => "humidity",
-        }
-    }
-
-    fn state_class(&self) -> &'static str {
-        match self {
-            SensorMeasureType::Temperature => "measurement",
-            SensorMeasureType::Humidity => "measurement",
-        }
-    }
-
-    fn unit_of_measurement(&self) -> &'static str {
-        match self {
-            SensorMeasureType::Temperature => "°C",
-            SensorMeasureType::Humidity => "%",
-        }
-    }
-}
-
 #[derive(Debug, Serialize, Default, PartialEq, Eq)]
 pub struct HADiscovery {
     pub payload: HASensor,
@@ -126,14 +136,15 @@ impl HADiscovery {
         measure: &SensorMeasureType,
         sensor_id: &str,
         device: HADevice,
+        units: &Units,
     ) -> Self {
-        let sensor = HASensor::new(measure, device, sensor_id);
+        let sensor = HASensor::new(measure, device, sensor_id, units);
         let topic = format!(
             "homeassistant/sensor/{}_{}_{}_{}/config",
             crate::APP_NAME,
             secure_mqtt_topic_name(&sensor.device.name),
             secure_mqtt_topic_name(sensor_id),
-            secure_mqtt_topic_name(&measure.name()),
+            secure_mqtt_topic_name(measure.key()),
         );
 
         Self {
@@ -150,9 +161,10 @@ mod tests {
         secure_mqtt_topic_name, HADiscovery,
     };
     use crate::publisher::mqtt::MQTT_STATE_TOPIC_BASE;
+    use crate::publisher::{TemperatureUnit, Units};
     use crate::sensor::SensorMeasureType;
 
-    use super::{HADevice, HASensor};
+    use super::{HAAvailability, HADevice, HASensor};
     use rumqttc::valid_topic;
 
     #[test]
@@ -196,21 +208,42 @@ mod tests {
 
         let sensor_id = String::from("sensor-001");
         let measure = SensorMeasureType::Humidity;
-        let sensor = HASensor::new(&measure, device.clone(), &sensor_id);
+        let sensor = HASensor::new(
+            &measure,
+            device.clone(),
+            &sensor_id,
+            &Units::default(),
+        );
 
         assert_eq!(
             sensor,
             HASensor {
                 name: "Humidity".into(),
-                device_class: "humidity",
+                device_class: Some("humidity"),
                 state_class: "measurement",
-                unit_of_measurement: "%",
+                unit_of_measurement: Some("%"),
+                entity_category: None,
                 state_topic: format!(
                     "{}/w_ird_ma_hine_n@me/sensor-001/state",
                     MQTT_STATE_TOPIC_BASE
                 ),
                 unique_id: "sensor-001_humidity".into(),
                 value_template: r"{{ value_json.humidity }}".into(),
+                availability: vec![
+                    HAAvailability {
+                        topic: format!(
+                            "{}/w_ird_ma_hine_n@me/availability",
+                            MQTT_STATE_TOPIC_BASE
+                        ),
+                    },
+                    HAAvailability {
+                        topic: format!(
+                            "{}/w_ird_ma_hine_n@me/sensor-001/availability",
+                            MQTT_STATE_TOPIC_BASE
+                        ),
+                    },
+                ],
+                availability_mode: "all",
                 device,
             },
         );
@@ -227,7 +260,12 @@ mod tests {
 
         let sensor_id = String::from("sensor-001");
         let measure = SensorMeasureType::Humidity;
-        let discovery = HADiscovery::new(&measure, &sensor_id, device.clone());
+        let discovery = HADiscovery::new(
+            &measure,
+            &sensor_id,
+            device.clone(),
+            &Units::default(),
+        );
 
         assert_eq!(
         discovery.topic,
@@ -235,4 +273,106 @@ mod tests {
             .to_string()
     );
     }
+
+    #[test]
+    fn discovery_names() {
+        let device = HADevice {
+            name: "Device Name".into(),
+            ..Default::default()
+        };
+
+        let discovery = HADiscovery::new(
+            &SensorMeasureType::DewPoint,
+            "sensor-001",
+            device.clone(),
+            &Units::default(),
+        );
+        assert_eq!(discovery.payload.name, "Dew point");
+        assert_eq!(discovery.payload.unique_id, "sensor-001_dew_point");
+        assert_eq!(
+            discovery.topic,
+            "homeassistant/sensor/sensors-pub_device-name_sensor-001_dew_point/config"
+        );
+
+        let discovery = HADiscovery::new(
+            &SensorMeasureType::Load1m,
+            "sensor-001",
+            device,
+            &Units::default(),
+        );
+        assert_eq!(discovery.payload.name, "Load 1m");
+        assert_eq!(discovery.payload.unique_id, "sensor-001_load_1m");
+        assert_eq!(
+            discovery.topic,
+            "homeassistant/sensor/sensors-pub_device-name_sensor-001_load_1m/config"
+        );
+    }
+
+    #[test]
+    fn sensor_diagnostic() {
+        let device = HADevice {
+            name: "Device Name".into(),
+            manufacturer: "My Manufacturer".into(),
+            model: "Model XYZ".into(),
+            identifiers: vec!["Device Name".into()],
+        };
+
+        let sensor_id = String::from("sensor-001");
+        let measure = SensorMeasureType::Throttled;
+        let sensor = HASensor::new(
+            &measure,
+            device.clone(),
+            &sensor_id,
+            &Units::default(),
+        );
+
+        assert_eq!(sensor.device_class, None);
+        assert_eq!(sensor.unit_of_measurement, None);
+        assert_eq!(sensor.entity_category, Some("diagnostic"));
+        assert_eq!(sensor.value_template, r"{{ value_json.throttled }}");
+
+        let measure = SensorMeasureType::CpuTemperature;
+        let sensor =
+            HASensor::new(&measure, device, &sensor_id, &Units::default());
+
+        assert_eq!(sensor.unique_id, "sensor-001_cpu_temperature");
+        assert_eq!(sensor.entity_category, Some("diagnostic"));
+    }
+
+    #[test]
+    fn sensor_total_increasing() {
+        let device = HADevice::new(&ConfigDevice {
+            name: "Device Name".into(),
+            manufacturer: "My Manufacturer".into(),
+            model: "Model XYZ".into(),
+        });
+
+        let measure = SensorMeasureType::NetworkReceived;
+        let sensor = HASensor::new(&measure, device, "host", &Units::default());
+
+        assert_eq!(sensor.device_class, Some("data_size"));
+        assert_eq!(sensor.state_class, "total_increasing");
+        assert_eq!(sensor.unit_of_measurement, Some("GiB"));
+        assert_eq!(sensor.value_template, r"{{ value_json.network_received }}");
+    }
+
+    #[test]
+    fn sensor_converted_unit() {
+        let device = HADevice::new(&ConfigDevice {
+            name: "Device Name".into(),
+            manufacturer: "My Manufacturer".into(),
+            model: "Model XYZ".into(),
+        });
+        let units = Units {
+            temperature: TemperatureUnit::Fahrenheit,
+            ..Default::default()
+        };
+
+        let measure = SensorMeasureType::Temperature;
+        let sensor = HASensor::new(&measure, device, "sensor-001", &units);
+
+        assert_eq!(sensor.device_class, Some("temperature"));
+        assert_eq!(sensor.unit_of_measurement, Some("°F"));
+        assert_eq!(sensor.value_template, r"{{ value_json.temperature }}");
+    }
 }
@@ -1,18 +1,22 @@
 use async_trait::async_trait;
 use log::{debug, error, trace};
-use rumqttc::{AsyncClient, MqttOptions, QoS};
+use rumqttc::{AsyncClient, Event, LastWill, MqttOptions, Packet, QoS};
 use serde::*;
-use std::error::Error;
 use std::time::Duration;
 
 use super::ha_discovery::{HADevice, HADiscovery};
 use crate::config::ConfigDevice;
+use crate::publisher::{Payload, PayloadConfig, PublisherError};
 use crate::sensor::Measure;
 use crate::{Publisher, SensorMeasureType, APP_NAME};
 
+const ONLINE: &str = "online";
+const OFFLINE: &str = "offline";
+
 pub struct MqttPublisher {
     client: AsyncClient,
     ha_device: HADevice,
+    payload: PayloadConfig,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -21,6 +25,7 @@ pub struct MqttPublisherConfig {
     pub client_id: String,
     pub host: String,
     pub port: u16,
+    pub payload: PayloadConfig,
 }
 impl Default for MqttPublisherConfig {
     fn default() -> Self {
@@ -31,6 +36,7 @@ impl Default for MqttPublisherConfig {
                 .into(),
             host: "localhost".into(),
             port: 1883,
+            payload: PayloadConfig::default(),
         }
     }
 }
@@ -42,13 +48,33 @@ impl MqttPublisher {
         mqttoptions.set_keep_alive(Duration::from_secs(15));
 
         let ha_device = HADevice::new(device);
+        let availability_topic = ha_device.get_mqtt_availability_topic();
+        mqttoptions.set_last_will(LastWill::new(
+            &availability_topic,
+            OFFLINE,
+            QoS::AtLeastOnce,
+            true,
+        ));
 
         let (client, mut event_loop) = AsyncClient::new(mqttoptions, 10);
+        let connected_client = client.clone();
         tokio::spawn(async move {
             trace!("event loop started");
             loop {
                 let event = event_loop.poll().await;
                 match event {
+                    Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                        debug!("mqtt connected");
+                        // not awaited, the requests are sent by this loop
+                        if let Err(err) = connected_client.try_publish(
+                            &availability_topic,
+                            QoS::AtLeastOnce,
+                            true,
+                            ONLINE,
+                        ) {
+                            error!("{err}");
+                        }
+                    }
                     Ok(event) => {
                         trace!("{event:?}");
                     }
@@ -60,7 +86,11 @@ impl MqttPublisher {
             }
         });
 
-        Self { client, ha_device }
+        Self {
+            client,
+            ha_device,
+            payload: mqtt.payload.clone(),
+        }
     }
 }
 
@@ -70,9 +100,10 @@ impl Publisher for MqttPublisher {
         &self,
         measure: &Measure,
         sensor_id: &'a str,
-    ) -> Result<(), Box<dyn Error>> {
+    ) -> Result<(), PublisherError> {
         let topic = self.ha_device.get_mqtt_state_topic(sensor_id);
-        let payload = serde_json::to_string(&measure)?;
+        let payload =
+            serde_json::to_string(&Payload::new(measure, &self.payload))?;
 
         debug!("mqtt publish: {} => {}", topic, payload);
 
@@ -87,9 +118,13 @@ impl Publisher for MqttPublisher {
         &self,
         measure_type: &SensorMeasureType,
         sensor_id: &'a str,
-    ) -> Result<(), Box<dyn Error>> {
-        let discovery =
-            HADiscovery::new(measure_type, sensor_id, self.ha_device.clone());
+    ) -> Result<(), PublisherError> {
+        let discovery = HADiscovery::new(
+            measure_type,
+            sensor_id,
+            self.ha_device.clone(),
+            &self.payload.units,
+        );
 
         let payload = serde_json::to_string(&discovery.payload)?;
         self.client
@@ -99,4 +134,22 @@ impl Publisher for MqttPublisher {
 
         Ok(())
     }
+
+    async fn sensor_availability<'a>(
+        &self,
+        sensor_id: &'a str,
+        available: bool,
+    ) -> Result<(), PublisherError> {
+        let topic =
+            self.ha_device.get_mqtt_sensor_availability_topic(sensor_id);
+        let payload = if available { ONLINE } else { OFFLINE };
+
+        debug!("mqtt publish: {} => {}", topic, payload);
+
+        self.client
+            .publish(&topic, QoS::AtLeastOnce, true, payload)
+            .await?;
+
+        Ok(())
+    }
 }
@@ -0,0 +1,183 @@
+use serde::ser::SerializeMap;
+use serde::*;
+use std::time::UNIX_EPOCH;
+
+use crate::publisher::Units;
+use crate::sensor::Measure;
+
+/// What a publisher adds to the measure values.
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+#[serde(default)]
+pub struct PayloadConfig {
+    /// Add when the sample was taken
+    pub timestamp: Option<PayloadTimestamp>,
+    /// Add the read duration (`read_duration_ms`) and retry count
+    /// (`retries`)
+    pub metadata: bool,
+    /// Units the values are converted to
+    pub units: Units,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PayloadTimestamp {
+    /// `timestamp: 2024-01-20T18:04:10.123Z`
+    Rfc3339,
+    /// `timestamp_ms: 1705773850123`
+    EpochMs,
+    Both,
+}
+
+/// A measure as published, the values followed by what the config asks for.
+/// Values stay at the top level so `value_json.<key>` templates still match.
+pub struct Payload<'a> {
+    measure: &'a Measure,
+    config: &'a PayloadConfig,
+}
+
+impl<'a> Payload<'a> {
+    pub fn new(measure: &'a Measure, config: &'a PayloadConfig) -> Self {
+        Self { measure, config }
+    }
+}
+
+impl Serialize for Payload<'_> {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let metadata = &self.measure.metadata;
+        let mut map = serializer.serialize_map(None)?;
+
+        for (measure_type, value) in self.measure.iter() {
+            let value = self.config.units.convert(measure_type, *value);
+            map.serialize_entry(measure_type.key(), &value)?;
+        }
+
+        if let (Some(format), Some(time)) =
+            (self.config.timestamp, metadata.time)
+        {
+            if matches!(
+                format,
+                PayloadTimestamp::Rfc3339 | PayloadTimestamp::Both
+            ) {
+                let timestamp = humantime::format_rfc3339_millis(time);
+                map.serialize_entry("timestamp", &timestamp.to_string())?;
+            }
+            if matches!(
+                format,
+                PayloadTimestamp::EpochMs | PayloadTimestamp::Both
+            ) {
+                let epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+                map.serialize_entry(
+                    "timestamp_ms",
+                    &(epoch.as_millis() as u64),
+                )?;
+            }
+        }
+
+        if self.config.metadata {
+            if let Some(duration) = metadata.read_duration {
+                map.serialize_entry(
+                    "read_duration_ms",
+                    &(duration.as_millis() as u64),
+                )?;
+            }
+            map.serialize_entry("retries", &metadata.retries)?;
+        }
+
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use crate::{Measure, SensorMeasureType};
+
+    use crate::publisher::{TemperatureUnit, Units};
+
+    use super::{Payload, PayloadConfig, PayloadTimestamp};
+
+    fn measure() -> Measure {
+        let mut measure = Measure::from([
+            (SensorMeasureType::Temperature, 21.1),
+            (SensorMeasureType::Humidity, 61.8),
+        ]);
+        measure.metadata.time =
+            Some(UNIX_EPOCH + Duration::from_millis(1705773850123));
+        measure.metadata.read_duration = Some(Duration::from_millis(25));
+        measure.metadata.retries = 1;
+        measure
+    }
+
+    #[test]
+    fn payload_values_only() {
+        let measure = measure();
+        let config = PayloadConfig::default();
+
+        assert_eq!(
+            serde_json::to_string(&Payload::new(&measure, &config)).unwrap(),
+            r#"{"temperature":21.1,"humidity":61.8}"#
+        );
+    }
+
+    #[test]
+    fn payload_units() {
+        let measure = measure();
+        let config = PayloadConfig {
+            units: Units {
+                temperature: TemperatureUnit::Fahrenheit,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(
+            serde_json::to_string(&Payload::new(&measure, &config)).unwrap(),
+            r#"{"temperature":69.98,"humidity":61.8}"#
+        );
+    }
+
+    #[test]
+    fn payload_timestamp() {
+        let measure = measure();
+        let config = |timestamp| PayloadConfig {
+            timestamp: Some(timestamp),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            serde_json::to_string(&Payload::new(
+                &measure,
+                &config(PayloadTimestamp::Rfc3339)
+            ))
+            .unwrap(),
+            r#"{"temperature":21.1,"humidity":61.8,"timestamp":"2024-01-20T18:04:10.123Z"}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&Payload::new(
+                &measure,
+                &config(PayloadTimestamp::Both)
+            ))
+            .unwrap(),
+            r#"{"temperature":21.1,"humidity":61.8,"timestamp":"2024-01-20T18:04:10.123Z","timestamp_ms":1705773850123}"#
+        );
+    }
+
+    #[test]
+    fn payload_metadata() {
+        let measure = measure();
+        let config = PayloadConfig {
+            timestamp: Some(PayloadTimestamp::EpochMs),
+            metadata: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            serde_json::to_string(&Payload::new(&measure, &config)).unwrap(),
+            r#"{"temperature":21.1,"humidity":61.8,"timestamp_ms":1705773850123,"read_duration_ms":25,"retries":1}"#
+        );
+    }
+}
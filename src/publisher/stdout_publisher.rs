@@ -1,25 +1,31 @@
 use async_trait::async_trait;
 use serde::*;
-use std::error::Error;
 
+use crate::publisher::{Payload, PayloadConfig, PublisherError};
 use crate::sensor::Measure;
 use crate::{Publisher, SensorMeasureType};
 
-pub struct StdoutPublisher {}
+pub struct StdoutPublisher {
+    payload: PayloadConfig,
+}
 
 #[derive(Deserialize, Serialize, Debug, Default)]
 #[serde(default)]
-pub struct StdoutPublisherConfig {}
+pub struct StdoutPublisherConfig {
+    pub payload: PayloadConfig,
+}
 
 impl Default for StdoutPublisher {
     fn default() -> Self {
-        Self::new()
+        Self::new(&StdoutPublisherConfig::default())
     }
 }
 
 impl StdoutPublisher {
-    pub fn new() -> Self {
-        StdoutPublisher {}
+    pub fn new(config: &StdoutPublisherConfig) -> Self {
+        StdoutPublisher {
+            payload: config.payload.clone(),
+        }
     }
 }
 
@@ -29,8 +35,9 @@ impl Publisher for StdoutPublisher {
         &self,
         measure: &Measure,
         sensor_id: &'a str,
-    ) -> Result<(), Box<dyn Error>> {
-        let payload = serde_yaml::to_string(&measure)?;
+    ) -> Result<(), PublisherError> {
+        let payload =
+            serde_yaml::to_string(&Payload::new(measure, &self.payload))?;
         println!("-- {sensor_id} -- \n{payload}");
 
         Ok(())
@@ -40,8 +47,24 @@ impl Publisher for StdoutPublisher {
         &self,
         measure_type: &SensorMeasureType,
         sensor_id: &'a str,
-    ) -> Result<(), Box<dyn Error>> {
-        println!("@DECLARE {sensor_id} [{measure_type}]");
+    ) -> Result<(), PublisherError> {
+        match self.payload.units.unit(measure_type) {
+            Some(unit) => {
+                println!("@DECLARE {sensor_id} [{measure_type} ({unit})]")
+            }
+            None => println!("@DECLARE {sensor_id} [{measure_type}]"),
+        }
+
+        Ok(())
+    }
+
+    async fn sensor_availability<'a>(
+        &self,
+        sensor_id: &'a str,
+        available: bool,
+    ) -> Result<(), PublisherError> {
+        let availability = if available { "online" } else { "offline" };
+        println!("@AVAILABILITY {sensor_id} {availability}");
 
         Ok(())
     }
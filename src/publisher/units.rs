@@ -0,0 +1,157 @@
+use serde::*;
+
+use crate::sensor::round2;
+use crate::SensorMeasureType;
+
+/// Units published instead of the ones the sensors measure in, applied to
+/// every quantity in °C or hPa.
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct Units {
+    pub temperature: TemperatureUnit,
+    pub pressure: PressureUnit,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TemperatureUnit {
+    #[default]
+    Celsius,
+    Fahrenheit,
+    Kelvin,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PressureUnit {
+    #[default]
+    Hpa,
+    Kpa,
+    Mbar,
+    Inhg,
+    Mmhg,
+    Psi,
+}
+
+impl Units {
+    pub fn unit(
+        &self,
+        measure_type: &SensorMeasureType,
+    ) -> Option<&'static str> {
+        match measure_type.quantity().unit {
+            Some("°C") => Some(match self.temperature {
+                TemperatureUnit::Celsius => "°C",
+                TemperatureUnit::Fahrenheit => "°F",
+                TemperatureUnit::Kelvin => "K",
+            }),
+            Some("hPa") => Some(match self.pressure {
+                PressureUnit::Hpa => "hPa",
+                PressureUnit::Kpa => "kPa",
+                PressureUnit::Mbar => "mbar",
+                PressureUnit::Inhg => "inHg",
+                PressureUnit::Mmhg => "mmHg",
+                PressureUnit::Psi => "psi",
+            }),
+            unit => unit,
+        }
+    }
+
+    /// `value` of `measure_type` converted from the sensor unit, rounded at
+    /// 2 decimal
+    pub fn convert(&self, measure_type: &SensorMeasureType, value: f32) -> f32 {
+        let value = value as f64;
+        let converted = match measure_type.quantity().unit {
+            Some("°C") => match self.temperature {
+                TemperatureUnit::Celsius => return value as f32,
+                TemperatureUnit::Fahrenheit => value * 9.0 / 5.0 + 32.0,
+                TemperatureUnit::Kelvin => value + 273.15,
+            },
+            Some("hPa") => match self.pressure {
+                PressureUnit::Hpa | PressureUnit::Mbar => return value as f32,
+                PressureUnit::Kpa => value / 10.0,
+                PressureUnit::Inhg => value * 0.029_529_983,
+                PressureUnit::Mmhg => value * 0.750_061_68,
+                PressureUnit::Psi => value * 0.014_503_774,
+            },
+            _ => return value as f32,
+        };
+
+        round2(converted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::SensorMeasureType;
+
+    use super::{PressureUnit, TemperatureUnit, Units};
+
+    #[test]
+    fn units_default() {
+        let units = Units::default();
+
+        assert_eq!(units.unit(&SensorMeasureType::Temperature), Some("°C"));
+        assert_eq!(
+            units.convert(&SensorMeasureType::Temperature, 21.37),
+            21.37
+        );
+        assert_eq!(units.unit(&SensorMeasureType::Humidity), Some("%"));
+        assert_eq!(units.unit(&SensorMeasureType::Throttled), None);
+    }
+
+    #[test]
+    fn units_temperature() {
+        let units = |temperature| Units {
+            temperature,
+            ..Default::default()
+        };
+
+        let fahrenheit = units(TemperatureUnit::Fahrenheit);
+        assert_eq!(
+            fahrenheit.unit(&SensorMeasureType::Temperature),
+            Some("°F")
+        );
+        assert_eq!(
+            fahrenheit.convert(&SensorMeasureType::Temperature, 21.5),
+            70.7
+        );
+        assert_eq!(
+            fahrenheit.convert(&SensorMeasureType::CpuTemperature, -40.0),
+            -40.0
+        );
+        // other measures untouched
+        assert_eq!(
+            fahrenheit.convert(&SensorMeasureType::Humidity, 40.0),
+            40.0
+        );
+
+        let kelvin = units(TemperatureUnit::Kelvin);
+        assert_eq!(kelvin.unit(&SensorMeasureType::CpuTemperature), Some("K"));
+        assert_eq!(
+            kelvin.convert(&SensorMeasureType::Temperature, 0.0),
+            273.15
+        );
+    }
+
+    #[test]
+    fn units_pressure() {
+        let units = |pressure| Units {
+            pressure,
+            ..Default::default()
+        };
+
+        let inhg = units(PressureUnit::Inhg);
+        assert_eq!(inhg.unit(&SensorMeasureType::Pressure), Some("inHg"));
+        assert_eq!(inhg.convert(&SensorMeasureType::Pressure, 1013.25), 29.92);
+        assert_eq!(
+            units(PressureUnit::Kpa)
+                .convert(&SensorMeasureType::Pressure, 1013.25),
+            101.33
+        );
+        assert_eq!(
+            units(PressureUnit::Mmhg)
+                .convert(&SensorMeasureType::Pressure, 1013.25),
+            760.0
+        );
+    }
+}
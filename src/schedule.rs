@@ -0,0 +1,429 @@
+use rand::Rng;
+use serde::*;
+use std::mem::MaybeUninit;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::config::ConfigError;
+
+/// When a sensor is read, every `interval` by default.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct ScheduleConfig {
+    /// Read interval of this sensor instead of the global one
+    #[serde(with = "humantime_serde")]
+    pub interval: Option<Duration>,
+    /// Read on the interval boundaries of the local time, `5m` at :00, :05...
+    pub align: bool,
+    /// Read at the times of a cron expression instead, local time,
+    /// `minute hour day-of-month month day-of-week`
+    pub cron: Option<String>,
+    /// Each read delayed by a random duration up to this
+    #[serde(with = "humantime_serde")]
+    pub jitter: Option<Duration>,
+}
+
+impl ScheduleConfig {
+    /// The cron expression parsed, checked to match within 5 years
+    pub fn cron(&self) -> Result<Option<Cron>, ConfigError> {
+        let Some(expression) = &self.cron else {
+            return Ok(None);
+        };
+        let cron = expression.parse::<Cron>()?;
+        if cron.next(epoch(SystemTime::now()), |_| 0).is_none() {
+            return Err(ConfigError::Invalid(format!(
+                "Cron never matches: {expression:?}"
+            )));
+        }
+        Ok(Some(cron))
+    }
+}
+
+pub struct Schedule {
+    kind: ScheduleKind,
+    jitter: Option<Duration>,
+}
+
+enum ScheduleKind {
+    Every { period: Duration, align: bool },
+    Cron(Cron),
+}
+
+impl Schedule {
+    /// `period` is the time between two reads when there is no cron
+    pub fn new(
+        config: &ScheduleConfig,
+        period: Duration,
+    ) -> Result<Self, ConfigError> {
+        let kind = match config.cron()? {
+            Some(cron) => ScheduleKind::Cron(cron),
+            None if period.is_zero() => {
+                return Err(ConfigError::Invalid("Zero interval".into()))
+            }
+            None => ScheduleKind::Every {
+                period,
+                align: config.align,
+            },
+        };
+
+        Ok(Self {
+            kind,
+            jitter: config.jitter,
+        })
+    }
+
+    /// Time of the read following the one at `last`, `None` for the first
+    pub fn next(
+        &self,
+        last: Option<SystemTime>,
+        now: SystemTime,
+    ) -> SystemTime {
+        self.next_with_offset(last, now, utc_offset)
+    }
+
+    fn next_with_offset(
+        &self,
+        last: Option<SystemTime>,
+        now: SystemTime,
+        offset: impl Fn(i64) -> i64,
+    ) -> SystemTime {
+        match &self.kind {
+            ScheduleKind::Every {
+                period,
+                align: false,
+            } => match last {
+                // don't catch up on the reads missed by a slow sensor
+                Some(last) => (last + *period).max(now),
+                None => now,
+            },
+            ScheduleKind::Every {
+                period,
+                align: true,
+            } => {
+                let after = last.map_or(now, |last| last.max(now));
+                let secs = epoch(after);
+                let local = secs + offset(secs);
+                let period = period.as_secs().max(1) as i64;
+                let next = (local.div_euclid(period) + 1) * period;
+                from_epoch(next - offset(secs))
+            }
+            ScheduleKind::Cron(cron) => {
+                let after = last.map_or(now, |last| last.max(now));
+                cron.next(epoch(after), offset)
+                    .map(from_epoch)
+                    // checked in `new`, only for a far away last match
+                    .unwrap_or(after + Duration::from_secs(86400))
+            }
+        }
+    }
+
+    /// Random delay added to a read, not to the schedule so it doesn't drift
+    pub fn jitter(&self) -> Duration {
+        match self.jitter {
+            Some(jitter) if !jitter.is_zero() => {
+                rand::thread_rng().gen_range(Duration::ZERO..jitter)
+            }
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+fn epoch(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+fn from_epoch(secs: i64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(secs.max(0) as u64)
+}
+
+fn utc_offset(secs: i64) -> i64 {
+    let time = secs as libc::time_t;
+    let mut tm = MaybeUninit::<libc::tm>::uninit();
+    // SAFETY: `tm` is only read when `localtime_r` filled it.
+    let tm = unsafe {
+        if libc::localtime_r(&time, tm.as_mut_ptr()).is_null() {
+            return 0;
+        }
+        tm.assume_init()
+    };
+    // only 32 bits on 32 bits targets
+    let offset: libc::c_long = tm.tm_gmtoff;
+    offset as i64
+}
+
+/// A 5 fields cron expression, each field being `*`, a value, a range
+/// `a-b`, any of them with a step `/n`, or a list of them `a,b-c`.
+#[derive(Debug, PartialEq)]
+pub struct Cron {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool, // both days fields restricted, either matches
+}
+
+impl FromStr for Cron {
+    type Err = ConfigError;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let fields = string.split_whitespace().collect::<Vec<_>>();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(ConfigError::Invalid(format!(
+                "Cron needs 5 fields: {string:?}"
+            )));
+        };
+
+        let mut weekdays_bits = Self::field(weekdays, 0, 7)?;
+        // 7 is sunday too
+        if weekdays_bits & 1 << 7 != 0 {
+            weekdays_bits |= 1;
+        }
+
+        Ok(Self {
+            minutes: Self::field(minutes, 0, 59)?,
+            hours: Self::field(hours, 0, 23)?,
+            days: Self::field(days, 1, 31)?,
+            months: Self::field(months, 1, 12)?,
+            weekdays: weekdays_bits,
+            any_day: !days.starts_with('*') && !weekdays.starts_with('*'),
+        })
+    }
+}
+
+impl Cron {
+    /// Bits of the values matched by `field`
+    fn field(field: &str, min: u32, max: u32) -> Result<u64, ConfigError> {
+        let invalid =
+            || ConfigError::Invalid(format!("Invalid cron field: {field:?}"));
+        let number = |s: &str| s.parse::<u32>().map_err(|_| invalid());
+        let mut bits = 0u64;
+
+        for part in field.split(',') {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => (range, Some(number(step)?)),
+                None => (part, None),
+            };
+            let (start, end) = match range.split_once('-') {
+                _ if range == "*" => (min, max),
+                Some((start, end)) => (number(start)?, number(end)?),
+                // `5/15` is from 5 to the end
+                None if step.is_some() => (number(range)?, max),
+                None => (number(range)?, number(range)?),
+            };
+            let step = step.unwrap_or(1);
+            if step == 0 || start < min || end > max || start > end {
+                return Err(invalid());
+            }
+
+            bits |= (start..=end)
+                .step_by(step as usize)
+                .fold(0, |b, v| b | 1 << v);
+        }
+
+        Ok(bits)
+    }
+
+    fn is(bits: u64, value: u32) -> bool {
+        bits & 1 << value != 0
+    }
+
+    fn matches_day(&self, time: &CivilTime) -> bool {
+        let day = Self::is(self.days, time.day);
+        let weekday = Self::is(self.weekdays, time.weekday);
+        let day = if self.any_day {
+            day || weekday
+        } else {
+            day && weekday
+        };
+
+        Self::is(self.months, time.month) && day
+    }
+
+    /// First matching minute strictly after `secs`, within 5 years. Days and
+    /// hours not matching are skipped whole.
+    fn next(&self, secs: i64, offset: impl Fn(i64) -> i64) -> Option<i64> {
+        let end = secs + 5 * 366 * 86400;
+        let mut minute = secs.div_euclid(60) * 60 + 60;
+
+        while minute <= end {
+            let local = minute + offset(minute);
+            let time = CivilTime::from_epoch(local);
+            // to the start of the next local day or hour
+            minute += if !self.matches_day(&time) {
+                86400 - local.rem_euclid(86400)
+            } else if !Self::is(self.hours, time.hour) {
+                3600 - local.rem_euclid(3600)
+            } else if !Self::is(self.minutes, time.minute) {
+                60
+            } else {
+                return Some(minute);
+            };
+        }
+        None
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct CivilTime {
+    minute: u32,
+    hour: u32,
+    day: u32,
+    month: u32,
+    weekday: u32, // 0 is sunday
+}
+
+impl CivilTime {
+    /// Days to civil date from Howard Hinnant's `civil_from_days`
+    fn from_epoch(secs: i64) -> Self {
+        let days = secs.div_euclid(86400);
+        let seconds = secs.rem_euclid(86400);
+
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z - era * 146097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+
+        Self {
+            minute: (seconds / 60 % 60) as u32,
+            hour: (seconds / 3600) as u32,
+            day: day as u32,
+            month: month as u32,
+            // 1970-01-01 was a thursday
+            weekday: (days + 4).rem_euclid(7) as u32,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::{CivilTime, Cron, Schedule, ScheduleConfig};
+
+    // 2024-01-20T18:04:10Z, a saturday
+    const NOW: u64 = 1705773850;
+
+    fn at(secs: u64) -> std::time::SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn schedule_civil_time() {
+        assert_eq!(
+            CivilTime::from_epoch(NOW as i64),
+            CivilTime {
+                minute: 4,
+                hour: 18,
+                day: 20,
+                month: 1,
+                weekday: 6
+            }
+        );
+        // 2024-02-29T00:00:00Z
+        assert_eq!(CivilTime::from_epoch(1709164800).day, 29);
+    }
+
+    #[test]
+    fn schedule_every() {
+        let config = ScheduleConfig::default();
+        let schedule =
+            Schedule::new(&config, Duration::from_secs(300)).unwrap();
+        let next = |last, now| schedule.next_with_offset(last, now, |_| 0);
+
+        assert_eq!(next(None, at(NOW)), at(NOW));
+        assert_eq!(next(Some(at(NOW)), at(NOW + 2)), at(NOW + 300));
+        // late, not caught up
+        assert_eq!(next(Some(at(NOW)), at(NOW + 400)), at(NOW + 400));
+        assert!(Schedule::new(&config, Duration::ZERO).is_err());
+    }
+
+    #[test]
+    fn schedule_aligned() {
+        let config = ScheduleConfig {
+            align: true,
+            ..Default::default()
+        };
+        let schedule =
+            Schedule::new(&config, Duration::from_secs(300)).unwrap();
+
+        // 18:04:10 => 18:05:00
+        assert_eq!(
+            schedule.next_with_offset(None, at(NOW), |_| 0),
+            at(NOW + 50)
+        );
+        // the read at 18:05:00 finished => 18:10:00
+        assert_eq!(
+            schedule.next_with_offset(Some(at(NOW + 50)), at(NOW + 52), |_| 0),
+            at(NOW + 350)
+        );
+
+        // daily at local midnight, UTC+2
+        let schedule =
+            Schedule::new(&config, Duration::from_secs(86400)).unwrap();
+        assert_eq!(
+            schedule.next_with_offset(None, at(NOW), |_| 7200),
+            at(1705795200 - 7200)
+        );
+    }
+
+    #[test]
+    fn schedule_cron_fields() {
+        assert_eq!(Cron::field("*", 0, 5).unwrap(), 0b111111);
+        assert_eq!(Cron::field("*/2", 0, 5).unwrap(), 0b010101);
+        assert_eq!(Cron::field("1-3,5", 0, 5).unwrap(), 0b101110);
+        assert_eq!(Cron::field("3/1", 0, 5).unwrap(), 0b111000);
+        assert_eq!(Cron::field("1/2", 0, 5).unwrap(), 0b101010);
+        assert!(Cron::field("6", 0, 5).is_err());
+        assert!(Cron::field("3-1", 0, 5).is_err());
+        assert!(Cron::field("*/0", 0, 5).is_err());
+        assert!(Cron::field("a", 0, 5).is_err());
+        assert!("* * *".parse::<Cron>().is_err());
+        assert!(Schedule::new(
+            &ScheduleConfig {
+                cron: Some("0 0 31 2 *".into()),
+                ..Default::default()
+            },
+            Duration::ZERO
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn schedule_cron() {
+        let next = |cron: &str, secs: u64| {
+            let schedule = Schedule::new(
+                &ScheduleConfig {
+                    cron: Some(cron.into()),
+                    ..Default::default()
+                },
+                Duration::ZERO,
+            )
+            .unwrap();
+            schedule.next_with_offset(None, at(secs), |_| 0)
+        };
+
+        // 18:04:10 => 18:05
+        assert_eq!(next("*/5 * * * *", NOW), at(NOW + 50));
+        // => 18:30
+        assert_eq!(next("30 * * * *", NOW), at(NOW + 25 * 60 + 50));
+        // => monday 2024-01-22 07:00
+        assert_eq!(next("0 7 * * 1-5", NOW), at(1705906800));
+        // sunday as 7 => 2024-01-21 00:00
+        assert_eq!(next("0 0 * * 7", NOW), at(1705795200));
+        // day of month or day of week => 2024-01-21 (sunday) before the 25th
+        assert_eq!(next("0 0 25 * 0", NOW), at(1705795200));
+        // => 2024-02-29 12:30, then 2028-02-29 12:30
+        assert_eq!(next("30 12 29 2 *", NOW), at(1709209800));
+        assert_eq!(next("30 12 29 2 *", 1709209800), at(1835440200));
+        // local midnight, UTC+2 => 2024-01-20T22:00Z
+        let cron = "0 0 * * *".parse::<Cron>().unwrap();
+        assert_eq!(cron.next(NOW as i64, |_| 7200), Some(1705788000));
+    }
+}
@@ -1,31 +1,41 @@
 use crc::Crc;
 use log::debug;
-use rppal::i2c::I2c;
 use serde::*;
-use std::error::Error;
 use std::thread;
 use std::time::Duration;
 
-use crate::sensor::{Measure, SensorMeasureType};
+use crate::sensor::{I2cBus, Measure, SensorError, SensorMeasureType};
 use crate::Sensor;
 
 pub struct AM2320 {
-    i2c: I2c,
+    i2c: I2cBus,
+    address: u16,
     buffer: [u8; 8],
     measure_types: Vec<SensorMeasureType>,
 }
 
-#[derive(Deserialize, Serialize, Debug, Default)]
+#[derive(Deserialize, Serialize, Debug)]
 #[serde(default)]
-pub struct AM2320Config {}
+pub struct AM2320Config {
+    pub bus: u8,
+    pub address: u16,
+}
+
+impl Default for AM2320Config {
+    fn default() -> Self {
+        Self {
+            bus: 1,
+            address: AM2320::I2C_ADDR,
+        }
+    }
+}
 
 impl AM2320 {
     const I2C_ADDR: u16 = 0x5c;
     const CRC: Crc<u16> = Crc::<u16>::new(&crc::CRC_16_MODBUS);
 
-    pub fn new() -> Result<Self, Box<dyn Error>> {
-        let mut i2c = I2c::new()?;
-        i2c.set_slave_address(Self::I2C_ADDR)?;
+    pub fn new(config: &AM2320Config) -> Result<Self, SensorError> {
+        let i2c = I2cBus::get(config.bus)?;
         let buffer = [0u8; 8];
 
         let measure_types =
@@ -33,19 +43,21 @@ impl AM2320 {
 
         Ok(AM2320 {
             i2c,
+            address: config.address,
             buffer,
             measure_types,
         })
     }
 
-    fn measure_from(bytes: [u8; 8]) -> Result<Measure, String> {
+    fn measure_from(bytes: [u8; 8]) -> Result<Measure, SensorError> {
         let crc = Self::CRC.checksum(&bytes);
         if crc != 0 {
-            return Err(format!("CRC error: 0x{:04X}", crc));
+            return Err(SensorError::Crc(format!("0x{:04X}", crc)));
         }
 
-        Ok(Measure {
-            temperature: Some(
+        Ok(Measure::from([
+            (
+                SensorMeasureType::Temperature,
                 match (
                     bytes[4] & 0x80 > 0,
                     i16::from_be_bytes([bytes[4] & 0x7F, bytes[5]]) as f64,
@@ -54,33 +66,36 @@ impl AM2320 {
                     (false, v) => (v * 0.1) as f32,
                 },
             ),
-            humidity: Some(
+            (
+                SensorMeasureType::Humidity,
                 (u16::from_be_bytes([bytes[2], bytes[3]]) as f64 * 0.1) as f32,
             ),
-        })
+        ]))
     }
 }
 
 impl Sensor for AM2320 {
-    fn measure(&mut self) -> Result<Measure, Box<dyn Error>> {
+    fn measure(&mut self) -> Result<Measure, SensorError> {
+        let mut i2c = self.i2c.lock(self.address)?;
+
         // AM2320 won't ACK if in sleeping mode
-        if self.i2c.write(&[0x00]).is_err() {
+        if i2c.write(&[0x00]).is_err() {
             thread::sleep(Duration::from_millis(3)); // wakeup take 0.8...3ms.
         }
 
         // 0x03 : read
         // 0x00 : from $00
         // 0x04 : to   $04
-        self.i2c.write(&[0x03, 0x00, 0x04])?;
+        i2c.write(&[0x03, 0x00, 0x04])?;
 
         // wait for measure done
         thread::sleep(Duration::from_nanos(1600));
 
         // read results
-        self.i2c.read(&mut self.buffer)?;
+        i2c.read(&mut self.buffer)?;
         debug!("read: {:02X?}", self.buffer);
 
-        Ok(AM2320::measure_from(self.buffer)?)
+        AM2320::measure_from(self.buffer)
     }
 
     fn measure_types(&self) -> &Vec<SensorMeasureType> {
@@ -92,7 +107,7 @@ impl Sensor for AM2320 {
 mod tests {
     use crate::sensor::am2320::AM2320;
 
-    use super::Measure;
+    use super::{Measure, SensorError, SensorMeasureType};
 
     #[test]
     fn am2320_basic_measure() {
@@ -103,10 +118,10 @@ mod tests {
 
         assert_eq!(
             measure,
-            Measure {
-                humidity: Some(61.8),
-                temperature: Some(21.1),
-            }
+            Measure::from([
+                (SensorMeasureType::Humidity, 61.8),
+                (SensorMeasureType::Temperature, 21.1)
+            ])
         )
     }
 
@@ -117,7 +132,7 @@ mod tests {
         ])
         .unwrap();
 
-        assert_eq!(measure.temperature, Some(-10.0))
+        assert_eq!(measure.get(&SensorMeasureType::Temperature), Some(-10.0))
     }
 
     #[test]
@@ -130,9 +145,11 @@ mod tests {
 
     #[test]
     fn am2320_bad_crc() {
-        assert!(AM2320::measure_from([
-            0x03, 0x04, 0x12, 0x34, 0x56, 0x78, 0x00, 0x00,
-        ])
-        .is_err())
+        assert!(matches!(
+            AM2320::measure_from([
+                0x03, 0x04, 0x12, 0x34, 0x56, 0x78, 0x00, 0x00,
+            ]),
+            Err(SensorError::Crc(_))
+        ))
     }
 }
@@ -0,0 +1,321 @@
+use log::debug;
+use serde::*;
+use std::thread;
+use std::time::Duration;
+
+use crate::sensor::{round2, I2cBus, Measure, SensorError, SensorMeasureType};
+use crate::Sensor;
+
+pub struct Bme280 {
+    i2c: I2cBus,
+    address: u16,
+    calibration: Bme280Calibration,
+    has_humidity: bool,
+    measure_types: Vec<SensorMeasureType>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(default)]
+pub struct Bme280Config {
+    pub bus: u8,
+    /// 0x76 or 0x77 depending on the SDO pin
+    pub address: u16,
+}
+
+impl Default for Bme280Config {
+    fn default() -> Self {
+        Self {
+            bus: 1,
+            address: Bme280::I2C_ADDR,
+        }
+    }
+}
+
+/// Compensation coefficients burned into the chip NVM (datasheet 4.2.2).
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Bme280Calibration {
+    t1: u16,
+    t2: i16,
+    t3: i16,
+    p1: u16,
+    p2: i16,
+    p3: i16,
+    p4: i16,
+    p5: i16,
+    p6: i16,
+    p7: i16,
+    p8: i16,
+    p9: i16,
+    h1: u8,
+    h2: i16,
+    h3: u8,
+    h4: i16,
+    h5: i16,
+    h6: i8,
+}
+
+impl Bme280Calibration {
+    /// `tp` is read from 0x88..=0xA1, `h` from 0xE1..=0xE7.
+    fn from_bytes(tp: [u8; 26], h: [u8; 7]) -> Self {
+        let u = |i: usize| u16::from_le_bytes([tp[i], tp[i + 1]]);
+        let s = |i: usize| i16::from_le_bytes([tp[i], tp[i + 1]]);
+
+        Self {
+            t1: u(0),
+            t2: s(2),
+            t3: s(4),
+            p1: u(6),
+            p2: s(8),
+            p3: s(10),
+            p4: s(12),
+            p5: s(14),
+            p6: s(16),
+            p7: s(18),
+            p8: s(20),
+            p9: s(22),
+            h1: tp[25],
+            h2: i16::from_le_bytes([h[0], h[1]]),
+            h3: h[2],
+            h4: ((h[3] as i8 as i16) << 4) | (h[4] & 0x0F) as i16,
+            h5: ((h[5] as i8 as i16) << 4) | (h[4] >> 4) as i16,
+            h6: h[6] as i8,
+        }
+    }
+}
+
+impl Bme280 {
+    const I2C_ADDR: u16 = 0x76;
+
+    const CHIP_ID_BMP280: u8 = 0x58;
+    const CHIP_ID_BME280: u8 = 0x60;
+
+    const REG_CHIP_ID: u8 = 0xD0;
+    const REG_CALIB_TP: u8 = 0x88;
+    const REG_CALIB_H: u8 = 0xE1;
+    const REG_CTRL_HUM: u8 = 0xF2;
+    const REG_STATUS: u8 = 0xF3;
+    const REG_CTRL_MEAS: u8 = 0xF4;
+    const REG_DATA: u8 = 0xF7;
+
+    // oversampling x1 for each quantity (osrs_t, osrs_p), forced mode
+    const CTRL_HUM: u8 = 0b001;
+    const CTRL_MEAS: u8 = (0b001 << 5) | (0b001 << 2) | 0b01;
+
+    pub fn new(config: &Bme280Config) -> Result<Self, SensorError> {
+        let bus = I2cBus::get(config.bus)?;
+        let i2c = bus.lock(config.address)?;
+
+        let has_humidity = match i2c.smbus_read_byte(Self::REG_CHIP_ID)? {
+            Self::CHIP_ID_BME280 => true,
+            Self::CHIP_ID_BMP280 => false,
+            id => {
+                return Err(SensorError::Missing(format!(
+                    "Unknown chip id: 0x{:02X}",
+                    id
+                )))
+            }
+        };
+
+        let mut tp = [0u8; 26];
+        i2c.write_read(&[Self::REG_CALIB_TP], &mut tp)?;
+        let mut h = [0u8; 7];
+        if has_humidity {
+            i2c.write_read(&[Self::REG_CALIB_H], &mut h)?;
+        }
+        drop(i2c);
+        let calibration = Bme280Calibration::from_bytes(tp, h);
+        debug!("calibration: {:?}", calibration);
+
+        let mut measure_types =
+            vec![SensorMeasureType::Temperature, SensorMeasureType::Pressure];
+        if has_humidity {
+            measure_types.push(SensorMeasureType::Humidity);
+        }
+
+        Ok(Bme280 {
+            i2c: bus,
+            address: config.address,
+            calibration,
+            has_humidity,
+            measure_types,
+        })
+    }
+
+    /// Compensate raw `0xF7..=0xFE` data with the floating point formulas of
+    /// the datasheet (8.1). Pressure is returned in hPa.
+    fn measure_from(
+        calibration: &Bme280Calibration,
+        bytes: [u8; 8],
+        has_humidity: bool,
+    ) -> Measure {
+        let c = calibration;
+        let adc_p = ((bytes[0] as u32) << 12
+            | (bytes[1] as u32) << 4
+            | (bytes[2] as u32) >> 4) as f64;
+        let adc_t = ((bytes[3] as u32) << 12
+            | (bytes[4] as u32) << 4
+            | (bytes[5] as u32) >> 4) as f64;
+        let adc_h = u16::from_be_bytes([bytes[6], bytes[7]]) as f64;
+
+        // temperature
+        let var1 = (adc_t / 16384.0 - c.t1 as f64 / 1024.0) * c.t2 as f64;
+        let var2 =
+            (adc_t / 131072.0 - c.t1 as f64 / 8192.0).powi(2) * c.t3 as f64;
+        let t_fine = var1 + var2;
+        let temperature = t_fine / 5120.0;
+
+        // pressure
+        let mut var1 = t_fine / 2.0 - 64000.0;
+        let mut var2 = var1 * var1 * c.p6 as f64 / 32768.0;
+        var2 += var1 * c.p5 as f64 * 2.0;
+        var2 = var2 / 4.0 + c.p4 as f64 * 65536.0;
+        var1 = (c.p3 as f64 * var1 * var1 / 524288.0 + c.p2 as f64 * var1)
+            / 524288.0;
+        var1 = (1.0 + var1 / 32768.0) * c.p1 as f64;
+        let pressure = if var1 == 0.0 {
+            0.0 // avoid division by zero
+        } else {
+            let mut p = 1048576.0 - adc_p;
+            p = (p - var2 / 4096.0) * 6250.0 / var1;
+            let var1 = c.p9 as f64 * p * p / 2147483648.0;
+            let var2 = p * c.p8 as f64 / 32768.0;
+            p + (var1 + var2 + c.p7 as f64) / 16.0
+        };
+
+        // humidity
+        let humidity = has_humidity.then(|| {
+            let mut h = t_fine - 76800.0;
+            h = (adc_h - (c.h4 as f64 * 64.0 + c.h5 as f64 / 16384.0 * h))
+                * (c.h2 as f64 / 65536.0
+                    * (1.0
+                        + c.h6 as f64 / 67108864.0
+                            * h
+                            * (1.0 + c.h3 as f64 / 67108864.0 * h)));
+            h *= 1.0 - c.h1 as f64 * h / 524288.0;
+            h.clamp(0.0, 100.0)
+        });
+
+        let mut measure = Measure::from([
+            (SensorMeasureType::Temperature, round2(temperature)),
+            (SensorMeasureType::Pressure, round2(pressure / 100.0)),
+        ]);
+        if let Some(humidity) = humidity {
+            measure.set(&SensorMeasureType::Humidity, round2(humidity));
+        }
+
+        measure
+    }
+}
+
+impl Sensor for Bme280 {
+    fn measure(&mut self) -> Result<Measure, SensorError> {
+        let mut i2c = self.i2c.lock(self.address)?;
+
+        // ctrl_hum is only applied after a write to ctrl_meas
+        if self.has_humidity {
+            i2c.write(&[Self::REG_CTRL_HUM, Self::CTRL_HUM])?;
+        }
+        i2c.write(&[Self::REG_CTRL_MEAS, Self::CTRL_MEAS])?;
+
+        // wait for measure done, ~8ms with x1 oversampling
+        let mut status = [0u8; 1];
+        for _ in 0..10 {
+            thread::sleep(Duration::from_millis(2));
+            i2c.write_read(&[Self::REG_STATUS], &mut status)?;
+            if status[0] & 0x08 == 0 {
+                break;
+            }
+        }
+
+        let mut buffer = [0u8; 8];
+        i2c.write_read(&[Self::REG_DATA], &mut buffer)?;
+        debug!("read: {:02X?}", buffer);
+
+        Ok(Bme280::measure_from(
+            &self.calibration,
+            buffer,
+            self.has_humidity,
+        ))
+    }
+
+    fn measure_types(&self) -> &Vec<SensorMeasureType> {
+        &self.measure_types
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Bme280, Bme280Calibration, SensorMeasureType};
+
+    // values from the BMP280 datasheet compensation example (3.12)
+    const CALIBRATION: Bme280Calibration = Bme280Calibration {
+        t1: 27504,
+        t2: 26435,
+        t3: -1000,
+        p1: 36477,
+        p2: -10685,
+        p3: 3024,
+        p4: 2855,
+        p5: 140,
+        p6: -7,
+        p7: 15500,
+        p8: -14600,
+        p9: 6000,
+        h1: 75,
+        h2: 362,
+        h3: 0,
+        h4: 313,
+        h5: 50,
+        h6: 30,
+    };
+
+    #[test]
+    fn bme280_calibration_from_bytes() {
+        let mut tp = [0u8; 26];
+        tp[0..2].copy_from_slice(&27504u16.to_le_bytes());
+        tp[2..4].copy_from_slice(&26435i16.to_le_bytes());
+        tp[4..6].copy_from_slice(&(-1000i16).to_le_bytes());
+        tp[25] = 75;
+        let h = [0x6A, 0x01, 0x00, 0x13, 0x29, 0x03, 0x1E];
+
+        let calibration = Bme280Calibration::from_bytes(tp, h);
+
+        assert_eq!(calibration.t1, 27504);
+        assert_eq!(calibration.t2, 26435);
+        assert_eq!(calibration.t3, -1000);
+        assert_eq!(calibration.h1, 75);
+        assert_eq!(calibration.h2, 362);
+        assert_eq!(calibration.h4, 313);
+        assert_eq!(calibration.h5, 50);
+        assert_eq!(calibration.h6, 30);
+    }
+
+    #[test]
+    fn bmp280_datasheet_measure() {
+        // adc_P = 415148, adc_T = 519888
+        let measure = Bme280::measure_from(
+            &CALIBRATION,
+            [0x65, 0x5A, 0xC0, 0x7E, 0xED, 0x00, 0x00, 0x00],
+            false,
+        );
+
+        assert_eq!(measure.get(&SensorMeasureType::Temperature), Some(25.08));
+        assert_eq!(measure.get(&SensorMeasureType::Pressure), Some(1006.53));
+        assert_eq!(measure.get(&SensorMeasureType::Humidity), None);
+    }
+
+    #[test]
+    fn bme280_humidity_in_range() {
+        for adc_h in [0x0000u16, 0x6000, 0xFFFF] {
+            let [msb, lsb] = adc_h.to_be_bytes();
+            let measure = Bme280::measure_from(
+                &CALIBRATION,
+                [0x65, 0x5A, 0xC0, 0x7E, 0xED, 0x00, msb, lsb],
+                true,
+            );
+
+            let humidity = measure.get(&SensorMeasureType::Humidity).unwrap();
+            assert!((0.0..=100.0).contains(&humidity));
+        }
+    }
+}
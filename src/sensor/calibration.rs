@@ -0,0 +1,202 @@
+use serde::*;
+use std::collections::HashMap;
+
+use crate::sensor::{round2, Measure, SensorError, SensorMeasureType};
+use crate::Sensor;
+
+/// Measures of another sensor corrected before they are published.
+pub struct Calibrated {
+    sensor: Box<dyn Sensor>,
+    calibrations: HashMap<SensorMeasureType, CalibrationConfig>,
+}
+
+/// The `points` table corrects the raw value first, interpolating between
+/// the two closest `[raw, true]` points (the first and last segments are
+/// extended), then `value * scale + offset` is applied.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct CalibrationConfig {
+    pub offset: f32,
+    pub scale: f32,
+    pub points: Vec<(f32, f32)>,
+}
+
+impl Default for CalibrationConfig {
+    fn default() -> Self {
+        Self {
+            offset: 0.0,
+            scale: 1.0,
+            points: vec![],
+        }
+    }
+}
+
+impl CalibrationConfig {
+    pub fn apply(&self, raw: f32) -> f32 {
+        let value =
+            Self::interpolate(&self.points, raw) * self.scale + self.offset;
+
+        round2(value)
+    }
+
+    /// `points` sorted by raw value
+    fn interpolate(points: &[(f32, f32)], raw: f32) -> f32 {
+        let segment = match points {
+            [] => return raw,
+            [(r, t)] => return raw - r + t,
+            _ => {
+                let i = points
+                    .iter()
+                    .position(|(r, _)| raw < *r)
+                    .unwrap_or(points.len())
+                    .clamp(1, points.len() - 1);
+                (points[i - 1], points[i])
+            }
+        };
+
+        let ((r0, t0), (r1, t1)) = segment;
+        t0 + (raw - r0) * (t1 - t0) / (r1 - r0)
+    }
+}
+
+impl Calibrated {
+    pub fn new(
+        sensor: Box<dyn Sensor>,
+        calibrations: &HashMap<SensorMeasureType, CalibrationConfig>,
+    ) -> Result<Self, SensorError> {
+        if let Some(t) = calibrations
+            .keys()
+            .find(|t| !sensor.measure_types().contains(t))
+        {
+            return Err(SensorError::Config(format!(
+                "{} calibrated but not measured",
+                t.key()
+            )));
+        }
+
+        let mut calibrations = calibrations.clone();
+
+        for (measure_type, calibration) in &mut calibrations {
+            calibration.points.sort_by(|a, b| a.0.total_cmp(&b.0));
+            if calibration.points.windows(2).any(|w| w[0].0 == w[1].0) {
+                return Err(SensorError::Config(format!(
+                    "{measure_type} calibration has duplicate raw points"
+                )));
+            }
+        }
+
+        Ok(Self {
+            sensor,
+            calibrations,
+        })
+    }
+}
+
+impl Sensor for Calibrated {
+    fn measure(&mut self) -> Result<Measure, SensorError> {
+        let mut measure = self.sensor.measure()?;
+
+        for (measure_type, value) in measure.iter_mut() {
+            if let Some(calibration) = self.calibrations.get(measure_type) {
+                *value = calibration.apply(*value);
+            }
+        }
+
+        Ok(measure)
+    }
+
+    fn measure_types(&self) -> &Vec<SensorMeasureType> {
+        self.sensor.measure_types()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{Faker, FakerConfig, SensorError, SensorMeasureType};
+
+    use super::{Calibrated, CalibrationConfig};
+
+    #[test]
+    fn calibration_linear() {
+        let calibration = CalibrationConfig {
+            offset: -1.2,
+            ..Default::default()
+        };
+        assert_eq!(calibration.apply(22.5), 21.3);
+
+        let calibration = CalibrationConfig {
+            offset: 0.5,
+            scale: 1.1,
+            ..Default::default()
+        };
+        assert_eq!(calibration.apply(50.0), 55.5);
+        assert_eq!(CalibrationConfig::default().apply(12.34), 12.34);
+    }
+
+    #[test]
+    fn calibration_points() {
+        let calibration = CalibrationConfig {
+            points: vec![(0.0, 1.0), (20.0, 19.0), (40.0, 38.0)],
+            ..Default::default()
+        };
+
+        assert_eq!(calibration.apply(0.0), 1.0);
+        assert_eq!(calibration.apply(10.0), 10.0);
+        assert_eq!(calibration.apply(30.0), 28.5);
+        // extrapolated from the closest segment
+        assert_eq!(calibration.apply(-10.0), -8.0);
+        assert_eq!(calibration.apply(50.0), 47.5);
+
+        let calibration = CalibrationConfig {
+            points: vec![(20.0, 19.0)],
+            offset: 0.25,
+            scale: 2.0,
+        };
+        assert_eq!(calibration.apply(25.0), 48.25);
+    }
+
+    #[test]
+    fn calibration_sensor() {
+        let faker = || {
+            Box::new(Faker::new(&FakerConfig {
+                measures: vec![SensorMeasureType::Temperature],
+            }))
+        };
+        let calibrations = |points| {
+            HashMap::from([(
+                SensorMeasureType::Temperature,
+                CalibrationConfig {
+                    points,
+                    ..Default::default()
+                },
+            )])
+        };
+
+        assert!(Calibrated::new(
+            faker(),
+            &calibrations(vec![(30.0, 29.0), (10.0, 9.0)])
+        )
+        .is_ok());
+        assert!(Calibrated::new(
+            faker(),
+            &calibrations(vec![(10.0, 9.0), (10.0, 9.5)])
+        )
+        .is_err());
+
+        assert!(matches!(
+            Calibrated::new(
+                faker(),
+                &HashMap::from([(
+                    SensorMeasureType::Humidity,
+                    CalibrationConfig {
+                        offset: 10.0,
+                        ..Default::default()
+                    },
+                )]),
+            ),
+            Err(SensorError::Config(_))
+        ));
+    }
+}
@@ -0,0 +1,202 @@
+use serde::*;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::sensor::{Measure, SensorMeasureType};
+
+/// Smallest change of a measure worth publishing, `0.5` or `"2%"` of the
+/// last value published. From a last value of 0 any change is a 100% one.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(untagged)]
+pub enum MinChange {
+    Absolute(f32),
+    Percent(#[serde(with = "percent")] f32),
+}
+
+mod percent {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        value: &f32,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{value}%"))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<f32, D::Error> {
+        let string = String::deserialize(deserializer)?;
+        string
+            .trim()
+            .strip_suffix('%')
+            .and_then(|s| s.trim().parse().ok())
+            .ok_or_else(|| de::Error::custom(format!("Not a %: {string:?}")))
+    }
+}
+
+impl MinChange {
+    fn exceeded(&self, last: f32, value: f32) -> bool {
+        let change = (value - last).abs();
+        match *self {
+            MinChange::Absolute(min) => change >= min,
+            MinChange::Percent(min) => {
+                change > 0.0 && change >= min / 100.0 * last.abs()
+            }
+        }
+    }
+}
+
+/// What was last published for a sensor, to publish a measure only when one
+/// of the thresholded values moved enough or after `max_silence`. Values
+/// without a threshold are published along but never trigger a publish.
+pub struct DeadBand {
+    min_change: HashMap<SensorMeasureType, MinChange>,
+    max_silence: Option<Duration>,
+    last: Option<(Measure, Instant)>,
+}
+
+impl DeadBand {
+    pub fn new(
+        min_change: &HashMap<SensorMeasureType, MinChange>,
+        max_silence: Option<Duration>,
+    ) -> Self {
+        Self {
+            min_change: min_change.clone(),
+            max_silence,
+            last: None,
+        }
+    }
+
+    /// Whether `measure` taken at `now` is published, remembered if it is.
+    pub fn publish(&mut self, measure: &Measure, now: Instant) -> bool {
+        let publish = match &self.last {
+            Some((last, time)) if !self.min_change.is_empty() => {
+                let silent = self.max_silence.is_some_and(|s| now - *time >= s);
+                silent || self.moved(last, measure)
+            }
+            _ => true,
+        };
+
+        if publish {
+            self.last = Some((measure.clone(), now));
+        }
+        publish
+    }
+
+    fn moved(&self, last: &Measure, measure: &Measure) -> bool {
+        self.min_change.iter().any(|(measure_type, min_change)| {
+            match (last.get(measure_type), measure.get(measure_type)) {
+                (Some(last), Some(value)) => min_change.exceeded(last, value),
+                (None, Some(_)) => true,
+                _ => false,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::time::{Duration, Instant};
+
+    use crate::{Measure, SensorMeasureType};
+
+    use super::{DeadBand, MinChange};
+
+    fn measure(temperature: f32, humidity: f32) -> Measure {
+        Measure::from([
+            (SensorMeasureType::Temperature, temperature),
+            (SensorMeasureType::Humidity, humidity),
+        ])
+    }
+
+    #[test]
+    fn dead_band_min_change() {
+        let now = Instant::now();
+        let mut dead_band = DeadBand::new(
+            &HashMap::from([
+                (SensorMeasureType::Temperature, MinChange::Absolute(0.5)),
+                (SensorMeasureType::Humidity, MinChange::Percent(10.0)),
+            ]),
+            None,
+        );
+
+        assert!(dead_band.publish(&measure(20.0, 50.0), now));
+        assert!(!dead_band.publish(&measure(20.3, 54.0), now));
+        // from the last value published, not the last one read
+        assert!(dead_band.publish(&measure(20.5, 54.0), now));
+        assert!(!dead_band.publish(&measure(20.1, 54.0), now));
+        assert!(dead_band.publish(&measure(20.5, 48.6), now));
+    }
+
+    #[test]
+    fn dead_band_percent_of_zero() {
+        let now = Instant::now();
+        let mut dead_band = DeadBand::new(
+            &HashMap::from([(
+                SensorMeasureType::Temperature,
+                MinChange::Percent(10.0),
+            )]),
+            None,
+        );
+
+        assert!(dead_band.publish(&measure(0.0, 50.0), now));
+        assert!(!dead_band.publish(&measure(0.0, 50.0), now));
+        assert!(dead_band.publish(&measure(0.1, 50.0), now));
+    }
+
+    #[test]
+    fn dead_band_max_silence() {
+        let now = Instant::now();
+        let mut dead_band = DeadBand::new(
+            &HashMap::from([(
+                SensorMeasureType::Temperature,
+                MinChange::Absolute(0.5),
+            )]),
+            Some(Duration::from_secs(600)),
+        );
+
+        assert!(dead_band.publish(&measure(20.0, 50.0), now));
+        // humidity has no threshold
+        assert!(!dead_band.publish(&measure(20.0, 70.0), now));
+        assert!(!dead_band
+            .publish(&measure(20.0, 50.0), now + Duration::from_secs(599)));
+        assert!(dead_band
+            .publish(&measure(20.0, 50.0), now + Duration::from_secs(600)));
+        assert!(!dead_band
+            .publish(&measure(20.0, 50.0), now + Duration::from_secs(900)));
+    }
+
+    #[test]
+    fn dead_band_disabled() {
+        let now = Instant::now();
+        let mut dead_band = DeadBand::new(&HashMap::new(), None);
+
+        assert!(dead_band.publish(&measure(20.0, 50.0), now));
+        assert!(dead_band.publish(&measure(20.0, 50.0), now));
+    }
+
+    #[test]
+    fn dead_band_config() {
+        let min_change: HashMap<SensorMeasureType, MinChange> =
+            serde_yaml::from_str(
+                "{ temperature: 0.2, humidity: 2 %, pressure: 1 }",
+            )
+            .unwrap();
+
+        assert_eq!(
+            min_change[&SensorMeasureType::Temperature],
+            MinChange::Absolute(0.2)
+        );
+        assert_eq!(
+            min_change[&SensorMeasureType::Humidity],
+            MinChange::Percent(2.0)
+        );
+        assert_eq!(
+            min_change[&SensorMeasureType::Pressure],
+            MinChange::Absolute(1.0)
+        );
+        assert!(serde_yaml::from_str::<MinChange>("two").is_err());
+    }
+}
@@ -0,0 +1,229 @@
+use crate::sensor::{round2, Measure, SensorError, SensorMeasureType};
+use crate::Sensor;
+
+/// Psychrometric quantities computed from the temperature and humidity of
+/// another sensor, published along its measures.
+pub struct Derived {
+    sensor: Box<dyn Sensor>,
+    derived: Vec<SensorMeasureType>,
+    measure_types: Vec<SensorMeasureType>,
+}
+
+impl Derived {
+    pub fn new(
+        sensor: Box<dyn Sensor>,
+        derived: &[SensorMeasureType],
+    ) -> Result<Self, SensorError> {
+        if let Some(measure_type) = derived
+            .iter()
+            .find(|t| Self::derive(t, 20.0, 50.0).is_none())
+        {
+            return Err(SensorError::Config(format!(
+                "{measure_type} can't be derived"
+            )));
+        }
+        let types = sensor.measure_types();
+        if !types.contains(&SensorMeasureType::Temperature)
+            || !types.contains(&SensorMeasureType::Humidity)
+        {
+            return Err(SensorError::Config(
+                "Derived measures need temperature and humidity".into(),
+            ));
+        }
+
+        let mut measure_types = types.clone();
+        measure_types.extend(derived.iter().filter(|t| !types.contains(t)));
+
+        Ok(Self {
+            sensor,
+            derived: derived.to_vec(),
+            measure_types,
+        })
+    }
+
+    /// `measure_type` from a temperature in °C and a relative humidity in %,
+    /// `None` if it isn't a derived quantity
+    pub fn derive(
+        measure_type: &SensorMeasureType,
+        temperature: f32,
+        humidity: f32,
+    ) -> Option<f32> {
+        let (t, rh) = (temperature as f64, humidity as f64);
+
+        let value = match measure_type {
+            SensorMeasureType::DewPoint => Self::dew_point(t, rh),
+            SensorMeasureType::AbsoluteHumidity => {
+                Self::absolute_humidity(t, rh)
+            }
+            SensorMeasureType::HeatIndex => Self::heat_index(t, rh),
+            SensorMeasureType::Humidex => Self::humidex(t, rh),
+            _ => return None,
+        };
+
+        Some(round2(value))
+    }
+
+    /// Magnus formula, Sonntag 1990 constants
+    fn dew_point(t: f64, rh: f64) -> f64 {
+        const A: f64 = 17.62;
+        const B: f64 = 243.12;
+
+        let gamma = (rh.max(0.01) / 100.0).ln() + A * t / (B + t);
+        B * gamma / (A - gamma)
+    }
+
+    /// Water vapour density in g/m³
+    fn absolute_humidity(t: f64, rh: f64) -> f64 {
+        let saturation = 6.112 * (17.67 * t / (t + 243.5)).exp(); // hPa
+        saturation * rh * 2.1674 / (273.15 + t)
+    }
+
+    /// NWS heat index (Rothfusz regression and its adjustments), in °C
+    fn heat_index(t: f64, rh: f64) -> f64 {
+        let f = t * 9.0 / 5.0 + 32.0;
+
+        let simple = 0.5 * (f + 61.0 + (f - 68.0) * 1.2 + rh * 0.094);
+        let hi = if (simple + f) / 2.0 < 80.0 {
+            simple
+        } else {
+            let mut hi = -42.379 + 2.049_015_23 * f + 10.143_331_27 * rh
+                - 0.224_755_41 * f * rh
+                - 0.006_837_83 * f * f
+                - 0.054_817_17 * rh * rh
+                + 0.001_228_74 * f * f * rh
+                + 0.000_852_82 * f * rh * rh
+                - 0.000_001_99 * f * f * rh * rh;
+            if rh < 13.0 && (80.0..=112.0).contains(&f) {
+                hi -= (13.0 - rh) / 4.0
+                    * ((17.0 - (f - 95.0).abs()) / 17.0).sqrt();
+            } else if rh > 85.0 && (80.0..=87.0).contains(&f) {
+                hi += (rh - 85.0) / 10.0 * (87.0 - f) / 5.0;
+            }
+            hi
+        };
+
+        (hi - 32.0) * 5.0 / 9.0
+    }
+
+    /// Environment Canada humidex
+    fn humidex(t: f64, rh: f64) -> f64 {
+        let dew_point = Self::dew_point(t, rh) + 273.15;
+        let vapour_pressure =
+            6.11 * (5417.753 * (1.0 / 273.16 - 1.0 / dew_point)).exp();
+
+        t + 0.5555 * (vapour_pressure - 10.0)
+    }
+}
+
+impl Sensor for Derived {
+    fn measure(&mut self) -> Result<Measure, SensorError> {
+        let mut measure = self.sensor.measure()?;
+
+        if let (Some(temperature), Some(humidity)) = (
+            measure.get(&SensorMeasureType::Temperature),
+            measure.get(&SensorMeasureType::Humidity),
+        ) {
+            for measure_type in &self.derived {
+                if let Some(value) =
+                    Self::derive(measure_type, temperature, humidity)
+                {
+                    measure.set(measure_type, value);
+                }
+            }
+        }
+
+        Ok(measure)
+    }
+
+    fn measure_types(&self) -> &Vec<SensorMeasureType> {
+        &self.measure_types
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Faker, FakerConfig, Sensor, SensorMeasureType};
+
+    use super::Derived;
+
+    #[test]
+    fn derived_dew_point() {
+        let dew_point =
+            |t, rh| Derived::derive(&SensorMeasureType::DewPoint, t, rh);
+
+        assert_eq!(dew_point(20.0, 50.0), Some(9.26));
+        assert_eq!(dew_point(25.0, 100.0), Some(25.0));
+        assert_eq!(dew_point(-5.0, 80.0), Some(-7.92));
+    }
+
+    #[test]
+    fn derived_absolute_humidity() {
+        let absolute_humidity = |t, rh| {
+            Derived::derive(&SensorMeasureType::AbsoluteHumidity, t, rh)
+        };
+
+        assert_eq!(absolute_humidity(20.0, 50.0), Some(8.64));
+        assert_eq!(absolute_humidity(30.0, 80.0), Some(24.28));
+    }
+
+    #[test]
+    fn derived_heat_index() {
+        let heat_index =
+            |t, rh| Derived::derive(&SensorMeasureType::HeatIndex, t, rh);
+
+        // NWS table: 90 °F at 70 % => 106 °F
+        assert_eq!(heat_index(32.22, 70.0), Some(41.06));
+        // below 80 °F the simple formula is close to the temperature
+        assert_eq!(heat_index(20.0, 50.0), Some(19.36));
+    }
+
+    #[test]
+    fn derived_humidex() {
+        let humidex =
+            |t, rh| Derived::derive(&SensorMeasureType::Humidex, t, rh);
+
+        // Environment Canada table: 30 °C at 70 % => 41
+        assert_eq!(humidex(30.0, 70.0).map(f32::round), Some(41.0));
+        assert_eq!(
+            Derived::derive(&SensorMeasureType::Pressure, 30.0, 70.0),
+            None
+        );
+    }
+
+    #[test]
+    fn derived_sensor() {
+        let faker = || {
+            Box::new(Faker::new(&FakerConfig {
+                measures: vec![
+                    SensorMeasureType::Temperature,
+                    SensorMeasureType::Humidity,
+                ],
+            }))
+        };
+
+        let mut sensor =
+            Derived::new(faker(), &[SensorMeasureType::DewPoint]).unwrap();
+        assert_eq!(
+            sensor.measure_types(),
+            &vec![
+                SensorMeasureType::Temperature,
+                SensorMeasureType::Humidity,
+                SensorMeasureType::DewPoint
+            ]
+        );
+        let measure = sensor.measure().unwrap();
+        let dew_point = measure.get(&SensorMeasureType::DewPoint).unwrap();
+        assert!(
+            dew_point <= measure.get(&SensorMeasureType::Temperature).unwrap()
+        );
+
+        assert!(Derived::new(faker(), &[SensorMeasureType::Pressure]).is_err());
+        assert!(Derived::new(
+            Box::new(Faker::new(&FakerConfig {
+                measures: vec![SensorMeasureType::Temperature],
+            })),
+            &[SensorMeasureType::DewPoint]
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,253 @@
+use log::debug;
+use rppal::gpio::{Bias, Gpio, IoPin, Level, Mode};
+use serde::*;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::sensor::{Measure, SensorError, SensorMeasureType};
+use crate::Sensor;
+
+/// DHT22 (AM2302) or DHT11 on a GPIO pin, the single-wire protocol is
+/// bit-banged so reads can fail when the process is preempted.
+pub struct Dht22 {
+    pin: IoPin,
+    model: DhtModel,
+    last_read: Option<Instant>,
+    measure_types: Vec<SensorMeasureType>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(default)]
+pub struct Dht22Config {
+    /// BCM GPIO number
+    pub pin: u8,
+    pub model: DhtModel,
+}
+
+impl Default for Dht22Config {
+    fn default() -> Self {
+        Self {
+            pin: 4,
+            model: DhtModel::default(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DhtModel {
+    Dht11,
+    #[default]
+    Dht22,
+}
+
+impl Dht22 {
+    // a "1" bit is a ~70µs high pulse, a "0" bit ~27µs
+    const BIT_THRESHOLD: Duration = Duration::from_micros(50);
+    const PULSE_TIMEOUT: Duration = Duration::from_micros(500);
+    // sensor needs 2s between two reads
+    const READ_INTERVAL: Duration = Duration::from_secs(2);
+
+    pub fn new(config: &Dht22Config) -> Result<Self, SensorError> {
+        let mut pin = Gpio::new()?.get(config.pin)?.into_io(Mode::Input);
+        pin.set_bias(Bias::PullUp);
+
+        let measure_types =
+            vec![SensorMeasureType::Humidity, SensorMeasureType::Temperature];
+
+        Ok(Dht22 {
+            pin,
+            model: config.model,
+            last_read: None,
+            measure_types,
+        })
+    }
+
+    /// Busy wait until the pin is at `level`, returns the elapsed time.
+    fn wait_level(&self, level: Level) -> Result<Duration, SensorError> {
+        let start = Instant::now();
+        while self.pin.read() != level {
+            if start.elapsed() > Self::PULSE_TIMEOUT {
+                return Err(SensorError::Timeout(format!(
+                    "Waiting for {level}"
+                )));
+            }
+        }
+        Ok(start.elapsed())
+    }
+
+    fn read_pulses(&mut self) -> Result<[Duration; 40], SensorError> {
+        let start_signal = match self.model {
+            DhtModel::Dht11 => Duration::from_millis(18),
+            DhtModel::Dht22 => Duration::from_millis(1),
+        };
+
+        // start signal: pull the line low, then release it
+        self.pin.set_mode(Mode::Output);
+        self.pin.set_low();
+        thread::sleep(start_signal);
+        self.pin.set_high();
+        self.pin.set_mode(Mode::Input);
+
+        // response: 80µs low then 80µs high
+        self.wait_level(Level::Low)?;
+        self.wait_level(Level::High)?;
+        self.wait_level(Level::Low)?;
+
+        // data: each bit is 50µs low then a high pulse whose length is the bit
+        let mut pulses = [Duration::ZERO; 40];
+        for pulse in pulses.iter_mut() {
+            self.wait_level(Level::High)?;
+            *pulse = self.wait_level(Level::Low)?;
+        }
+
+        Ok(pulses)
+    }
+
+    fn bytes_from(pulses: &[Duration; 40]) -> [u8; 5] {
+        let mut bytes = [0u8; 5];
+        for (i, pulse) in pulses.iter().enumerate() {
+            if *pulse > Self::BIT_THRESHOLD {
+                bytes[i / 8] |= 0x80 >> (i % 8);
+            }
+        }
+        bytes
+    }
+
+    fn measure_from(
+        model: DhtModel,
+        bytes: [u8; 5],
+    ) -> Result<Measure, SensorError> {
+        let checksum =
+            bytes[..4].iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+        if checksum != bytes[4] {
+            return Err(SensorError::Crc(format!(
+                "0x{:02X} != 0x{:02X}",
+                checksum, bytes[4]
+            )));
+        }
+
+        let (humidity, temperature) = match model {
+            DhtModel::Dht11 => (
+                bytes[0] as f64 + bytes[1] as f64 * 0.1,
+                match (bytes[3] & 0x80 > 0, bytes[2] as f64) {
+                    (true, v) => -(v + (bytes[3] & 0x7F) as f64 * 0.1),
+                    (false, v) => v + bytes[3] as f64 * 0.1,
+                },
+            ),
+            DhtModel::Dht22 => (
+                u16::from_be_bytes([bytes[0], bytes[1]]) as f64 * 0.1,
+                match (
+                    bytes[2] & 0x80 > 0,
+                    u16::from_be_bytes([bytes[2] & 0x7F, bytes[3]]) as f64,
+                ) {
+                    (true, v) => v * -0.1,
+                    (false, v) => v * 0.1,
+                },
+            ),
+        };
+
+        Ok(Measure::from([
+            (SensorMeasureType::Temperature, temperature as f32),
+            (SensorMeasureType::Humidity, humidity as f32),
+        ]))
+    }
+}
+
+impl Sensor for Dht22 {
+    fn measure(&mut self) -> Result<Measure, SensorError> {
+        // a retry sooner than that would fail again
+        if let Some(last_read) = self.last_read {
+            thread::sleep(
+                Self::READ_INTERVAL.saturating_sub(last_read.elapsed()),
+            );
+        }
+        self.last_read = Some(Instant::now());
+
+        let pulses = self.read_pulses()?;
+        let bytes = Dht22::bytes_from(&pulses);
+        debug!("read: {:02X?}", bytes);
+
+        Dht22::measure_from(self.model, bytes)
+    }
+
+    fn measure_types(&self) -> &Vec<SensorMeasureType> {
+        &self.measure_types
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Dht22, DhtModel, Measure, SensorMeasureType};
+
+    #[test]
+    fn dht22_basic_measure() {
+        let measure = Dht22::measure_from(
+            DhtModel::Dht22,
+            [0x02, 0x8C, 0x01, 0x5F, 0xEE],
+        )
+        .unwrap();
+
+        assert_eq!(
+            measure,
+            Measure::from([
+                (SensorMeasureType::Humidity, 65.2),
+                (SensorMeasureType::Temperature, 35.1)
+            ])
+        )
+    }
+
+    #[test]
+    fn dht22_negative_temperature() {
+        let measure = Dht22::measure_from(
+            DhtModel::Dht22,
+            [0x02, 0x8C, 0x80, 0x65, 0x73],
+        )
+        .unwrap();
+
+        assert_eq!(measure.get(&SensorMeasureType::Temperature), Some(-10.1))
+    }
+
+    #[test]
+    fn dht11_basic_measure() {
+        let measure = Dht22::measure_from(
+            DhtModel::Dht11,
+            [0x28, 0x00, 0x19, 0x05, 0x46],
+        )
+        .unwrap();
+
+        assert_eq!(
+            measure,
+            Measure::from([
+                (SensorMeasureType::Humidity, 40.0),
+                (SensorMeasureType::Temperature, 25.5)
+            ])
+        )
+    }
+
+    #[test]
+    fn dht22_bad_checksum() {
+        assert!(Dht22::measure_from(
+            DhtModel::Dht22,
+            [0x02, 0x8C, 0x01, 0x5F, 0x00],
+        )
+        .is_err())
+    }
+
+    #[test]
+    fn dht22_bytes_from_pulses() {
+        let zero = Duration::from_micros(27);
+        let one = Duration::from_micros(70);
+        let mut pulses = [zero; 40];
+        // 0x02 0x8C 0x01 0x5F 0xEE
+        for bit in [
+            6, 8, 12, 13, 23, 25, 27, 28, 29, 30, 31, 32, 33, 34, 36, 37, 38,
+        ] {
+            pulses[bit] = one;
+        }
+
+        assert_eq!(Dht22::bytes_from(&pulses), [0x02, 0x8C, 0x01, 0x5F, 0xEE])
+    }
+}
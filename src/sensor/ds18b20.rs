@@ -1,32 +1,124 @@
-use std::error::Error;
+use std::collections::HashMap;
 use std::fs;
 
 use log::debug;
 use serde::{Deserialize, Serialize};
 
-use crate::{Measure, Sensor, SensorMeasureType};
+use crate::{Measure, Sensor, SensorError, SensorMeasureType};
 
 pub struct Ds18b20 {
     measure_types: Vec<SensorMeasureType>,
     path: String,
+    w1_slave: bool,
 }
 
 #[derive(Deserialize, Serialize, Debug, Default)]
 #[serde(default)]
 pub struct Ds18b20Config {
+    /// Serial of the probe, family 28 is assumed unless given as `10-...`
     pub identifier: String,
+    /// Register every thermometer found on the bus instead of `identifier`
+    pub discover: bool,
+    /// Sensor id by ROM id (`28-0122334455ff`) for discovered probes
+    pub names: HashMap<String, String>,
+    /// Read the raw scratchpad from `w1_slave` to check its CRC and reject
+    /// power-on reset and disconnected values
+    pub w1_slave: bool,
 }
 
 impl Ds18b20 {
+    const ONE_WIRE_DEVICES: &'static str = "/sys/bus/w1/devices";
     const ONE_WIRE_FAMILY: u16 = 0x28;
+    // DS18S20, DS1822, DS18B20, DS1825/MAX31850
+    const ONE_WIRE_FAMILIES: [&'static str; 4] = ["10", "22", "28", "3b"];
+    // power-on reset value and the one reported by a disconnected probe
+    const POWER_ON_RESET: isize = 85000;
+    const DISCONNECTED: isize = -127000;
 
-    pub fn new(config: &Ds18b20Config) -> Result<Self, Box<dyn Error>> {
+    pub fn new(config: &Ds18b20Config) -> Result<Self, SensorError> {
+        let rom_id = if config.identifier.contains('-') {
+            config.identifier.clone()
+        } else {
+            format!("{:x}-{}", Ds18b20::ONE_WIRE_FAMILY, config.identifier)
+        };
+
+        Self::from_rom_id(&rom_id, config)
+    }
+
+    /// Find every thermometer on the bus, returned with its sensor id made
+    /// from `sensor_id` and the ROM id unless named in config.
+    pub fn discover(
+        sensor_id: &str,
+        config: &Ds18b20Config,
+    ) -> Result<Vec<(String, Self)>, SensorError> {
+        let rom_ids = Self::rom_ids()?;
+        debug!("Discovered: {rom_ids:?}");
+        // retried later, a probe can enumerate after boot
+        if rom_ids.is_empty() {
+            return Err(SensorError::Missing("No DS18B20 found".into()));
+        }
+
+        rom_ids
+            .iter()
+            .map(|rom_id| {
+                Ok((
+                    Self::sensor_id(sensor_id, rom_id, &config.names),
+                    Self::from_rom_id(rom_id, config)?,
+                ))
+            })
+            .collect()
+    }
+
+    /// Sensor ids `discover` gives to the thermometers on the bus and to
+    /// those named in config, without creating them
+    pub fn sensor_ids(sensor_id: &str, config: &Ds18b20Config) -> Vec<String> {
+        let mut rom_ids = Self::rom_ids().unwrap_or_default();
+        rom_ids.extend(config.names.keys().cloned());
+        rom_ids.sort();
+        rom_ids.dedup();
+
+        rom_ids
+            .iter()
+            .map(|rom_id| Self::sensor_id(sensor_id, rom_id, &config.names))
+            .collect()
+    }
+
+    fn rom_ids() -> Result<Vec<String>, SensorError> {
+        let mut rom_ids = fs::read_dir(Self::ONE_WIRE_DEVICES)?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .filter(|name| Self::is_thermometer(name))
+            .collect::<Vec<_>>();
+        rom_ids.sort();
+        Ok(rom_ids)
+    }
+
+    fn is_thermometer(rom_id: &str) -> bool {
+        match rom_id.split_once('-') {
+            Some((family, serial)) => {
+                Self::ONE_WIRE_FAMILIES.contains(&family) && !serial.is_empty()
+            }
+            None => false,
+        }
+    }
+
+    fn sensor_id(
+        sensor_id: &str,
+        rom_id: &str,
+        names: &HashMap<String, String>,
+    ) -> String {
+        match names.get(rom_id) {
+            Some(name) => name.clone(),
+            None => format!("{sensor_id}-{rom_id}"),
+        }
+    }
+
+    fn from_rom_id(
+        rom_id: &str,
+        config: &Ds18b20Config,
+    ) -> Result<Self, SensorError> {
         let measure_types = vec![SensorMeasureType::Temperature];
-        let path = format!(
-            "/sys/bus/w1/devices/{:x}-{}",
-            Ds18b20::ONE_WIRE_FAMILY,
-            config.identifier
-        );
+        let path = format!("{}/{}", Self::ONE_WIRE_DEVICES, rom_id);
 
         debug!("Check if device exist at: {path}");
         let _ = fs::metadata(&path)?;
@@ -34,27 +126,91 @@ impl Ds18b20 {
         Ok(Self {
             measure_types,
             path,
+            w1_slave: config.w1_slave,
         })
     }
 
-    fn measure_from(string: &str) -> Result<Measure, Box<dyn Error>> {
+    fn measure_from(string: &str) -> Result<Measure, SensorError> {
         let string = string.strip_suffix('\n').unwrap_or(string);
         let temperature = string.parse::<isize>()? as f64 * 0.001;
 
-        Ok(Measure {
-            temperature: Some(temperature as f32),
-            ..Default::default()
-        })
+        Ok(Measure::from([(
+            SensorMeasureType::Temperature,
+            temperature as f32,
+        )]))
+    }
+
+    /// Parse `w1_slave`, the scratchpad with the CRC check done by the kernel
+    /// then the temperature:
+    /// ```text
+    /// 72 01 4b 46 7f ff 0e 10 57 : crc=57 YES
+    /// 72 01 4b 46 7f ff 0e 10 57 t=23125
+    /// ```
+    fn measure_from_w1_slave(string: &str) -> Result<Measure, SensorError> {
+        let mut lines = string.lines();
+        let (crc_line, t_line) = match (lines.next(), lines.next()) {
+            (Some(crc), Some(t)) => (crc, t),
+            _ => {
+                return Err(SensorError::Parse(format!(
+                    "Invalid w1_slave: {string:?}"
+                )))
+            }
+        };
+
+        let (scratchpad, crc) =
+            crc_line.split_once(" : ").ok_or_else(|| {
+                SensorError::Parse(format!("Invalid CRC line: {crc_line:?}"))
+            })?;
+        if !crc.ends_with("YES") {
+            return Err(SensorError::Crc(crc.into()));
+        }
+        // an all zero scratchpad has a valid CRC too
+        if scratchpad.split(' ').all(|b| b == "00") {
+            return Err(SensorError::Missing(
+                "Empty scratchpad, probe disconnected ?".into(),
+            ));
+        }
+
+        let t = t_line
+            .rsplit_once("t=")
+            .ok_or_else(|| {
+                SensorError::Parse(format!(
+                    "Invalid temperature line: {t_line:?}"
+                ))
+            })?
+            .1
+            .parse::<isize>()?;
+        match t {
+            Self::POWER_ON_RESET => {
+                Err(SensorError::Parse("Power-on reset value (85°C)".into()))
+            }
+            Self::DISCONNECTED => {
+                Err(SensorError::Missing("Disconnected value (-127°C)".into()))
+            }
+            t => Ok(Measure::from([(
+                SensorMeasureType::Temperature,
+                (t as f64 * 0.001) as f32,
+            )])),
+        }
     }
 }
 
 impl Sensor for Ds18b20 {
-    fn measure(&mut self) -> Result<crate::Measure, Box<dyn Error>> {
-        let string = fs::read_to_string(format!("{}/temperature", self.path))?;
+    fn measure(&mut self) -> Result<crate::Measure, SensorError> {
+        let file = if self.w1_slave {
+            "w1_slave"
+        } else {
+            "temperature"
+        };
+        let string = fs::read_to_string(format!("{}/{}", self.path, file))?;
 
         debug!("string: {string:?}");
 
-        let measure = Ds18b20::measure_from(&string)?;
+        let measure = if self.w1_slave {
+            Ds18b20::measure_from_w1_slave(&string)?
+        } else {
+            Ds18b20::measure_from(&string)?
+        };
 
         Ok(measure)
     }
@@ -66,7 +222,9 @@ impl Sensor for Ds18b20 {
 
 #[cfg(test)]
 mod tests {
-    use crate::Measure;
+    use std::collections::HashMap;
+
+    use crate::{Measure, SensorError, SensorMeasureType};
 
     use super::Ds18b20;
 
@@ -76,10 +234,7 @@ mod tests {
 
         assert_eq!(
             measure,
-            Measure {
-                temperature: Some(20.25),
-                ..Default::default()
-            }
+            Measure::from([(SensorMeasureType::Temperature, 20.25)])
         )
     }
 
@@ -89,10 +244,86 @@ mod tests {
 
         assert_eq!(
             measure,
-            Measure {
-                temperature: Some(-5.0),
-                ..Default::default()
-            }
+            Measure::from([(SensorMeasureType::Temperature, -5.0)])
+        )
+    }
+
+    #[test]
+    fn ds18b20_is_thermometer() {
+        assert!(Ds18b20::is_thermometer("28-0122334455ff"));
+        assert!(Ds18b20::is_thermometer("10-000802b5e2a1"));
+        assert!(Ds18b20::is_thermometer("3b-0000001a2b3c"));
+        assert!(!Ds18b20::is_thermometer("w1_bus_master1"));
+        assert!(!Ds18b20::is_thermometer("29-0000001a2b3c"));
+        assert!(!Ds18b20::is_thermometer("28-"));
+    }
+
+    #[test]
+    fn ds18b20_sensor_id() {
+        let names = HashMap::from([(
+            "28-0122334455ff".to_string(),
+            "fermenter-top".to_string(),
+        )]);
+
+        assert_eq!(
+            Ds18b20::sensor_id("probes", "28-0122334455ff", &names),
+            "fermenter-top"
+        );
+        assert_eq!(
+            Ds18b20::sensor_id("probes", "28-0a0b0c0d0e0f", &names),
+            "probes-28-0a0b0c0d0e0f"
+        );
+    }
+
+    #[test]
+    fn ds18b20_w1_slave_measure() {
+        let measure = Ds18b20::measure_from_w1_slave(
+            "72 01 4b 46 7f ff 0e 10 57 : crc=57 YES\n\
+             72 01 4b 46 7f ff 0e 10 57 t=23125\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            measure,
+            Measure::from([(SensorMeasureType::Temperature, 23.125)])
+        )
+    }
+
+    #[test]
+    fn ds18b20_w1_slave_bad_crc() {
+        assert!(matches!(
+            Ds18b20::measure_from_w1_slave(
+                "72 01 4b 46 7f ff 0e 10 00 : crc=57 NO\n\
+                 72 01 4b 46 7f ff 0e 10 00 t=23125\n",
+            ),
+            Err(SensorError::Crc(_))
+        ))
+    }
+
+    #[test]
+    fn ds18b20_w1_slave_power_on_reset() {
+        assert!(matches!(
+            Ds18b20::measure_from_w1_slave(
+                "50 05 4b 46 7f ff 0c 10 1c : crc=1c YES\n\
+                 50 05 4b 46 7f ff 0c 10 1c t=85000\n",
+            ),
+            Err(SensorError::Parse(_))
+        ))
+    }
+
+    #[test]
+    fn ds18b20_w1_slave_disconnected() {
+        assert!(Ds18b20::measure_from_w1_slave(
+            "00 00 00 00 00 00 00 00 00 : crc=00 YES\n\
+             00 00 00 00 00 00 00 00 00 t=0\n",
         )
+        .is_err());
+        assert!(matches!(
+            Ds18b20::measure_from_w1_slave(
+                "81 f8 4b 46 7f ff 0f 10 5d : crc=5d YES\n\
+                 81 f8 4b 46 7f ff 0f 10 5d t=-127000\n",
+            ),
+            Err(SensorError::Missing(_))
+        ))
     }
 }
@@ -0,0 +1,123 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::num::{ParseFloatError, ParseIntError};
+
+/// Why a sensor couldn't be created or read, so a caller can tell a glitch
+/// worth retrying from a device that is not there.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SensorError {
+    /// I2C, GPIO, file or command failure
+    Io(String),
+    /// Data read corrupted on the wire
+    Crc(String),
+    /// Data read not what was expected
+    Parse(String),
+    /// No answer in time
+    Timeout(String),
+    /// Nothing at the configured bus, address, pin or path
+    Missing(String),
+    /// Invalid sensor configuration or options
+    Config(String),
+}
+
+impl Display for SensorError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SensorError::Io(msg) => write!(f, "I/O error: {msg}"),
+            SensorError::Crc(msg) => write!(f, "CRC error: {msg}"),
+            SensorError::Parse(msg) => write!(f, "Parse error: {msg}"),
+            SensorError::Timeout(msg) => write!(f, "Timeout: {msg}"),
+            SensorError::Missing(msg) => write!(f, "Device missing: {msg}"),
+            SensorError::Config(msg) => write!(f, "Invalid config: {msg}"),
+        }
+    }
+}
+
+impl Error for SensorError {}
+
+impl From<io::Error> for SensorError {
+    fn from(err: io::Error) -> Self {
+        match (err.kind(), err.raw_os_error()) {
+            (io::ErrorKind::NotFound, _) => {
+                SensorError::Missing(err.to_string())
+            }
+            (io::ErrorKind::TimedOut, _) => {
+                SensorError::Timeout(err.to_string())
+            }
+            // nothing ACKed at the I2C address
+            (_, Some(libc::ENXIO | libc::EREMOTEIO)) => {
+                SensorError::Missing(err.to_string())
+            }
+            _ => SensorError::Io(err.to_string()),
+        }
+    }
+}
+
+impl From<rppal::i2c::Error> for SensorError {
+    fn from(err: rppal::i2c::Error) -> Self {
+        match err {
+            rppal::i2c::Error::Io(err) => err.into(),
+            err => SensorError::Io(err.to_string()),
+        }
+    }
+}
+
+impl From<rppal::gpio::Error> for SensorError {
+    fn from(err: rppal::gpio::Error) -> Self {
+        match err {
+            rppal::gpio::Error::Io(err) => err.into(),
+            rppal::gpio::Error::PinNotAvailable(_) => {
+                SensorError::Missing(err.to_string())
+            }
+            err => SensorError::Io(err.to_string()),
+        }
+    }
+}
+
+impl From<ParseFloatError> for SensorError {
+    fn from(err: ParseFloatError) -> Self {
+        SensorError::Parse(err.to_string())
+    }
+}
+
+impl From<ParseIntError> for SensorError {
+    fn from(err: ParseIntError) -> Self {
+        SensorError::Parse(err.to_string())
+    }
+}
+
+impl From<serde_json::Error> for SensorError {
+    fn from(err: serde_json::Error) -> Self {
+        SensorError::Parse(err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::SensorError;
+
+    #[test]
+    fn sensor_error_from_io() {
+        let error = |err: io::Error| SensorError::from(err);
+
+        assert!(matches!(
+            error(io::ErrorKind::NotFound.into()),
+            SensorError::Missing(_)
+        ));
+        assert!(matches!(
+            error(io::ErrorKind::TimedOut.into()),
+            SensorError::Timeout(_)
+        ));
+        assert!(matches!(
+            error(io::Error::from_raw_os_error(libc::EREMOTEIO)),
+            SensorError::Missing(_)
+        ));
+        assert!(matches!(
+            error(io::Error::from_raw_os_error(libc::EIO)),
+            SensorError::Io(_)
+        ));
+    }
+}
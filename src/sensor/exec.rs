@@ -0,0 +1,245 @@
+use std::io::Read;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::{Measure, Sensor, SensorError, SensorMeasureType};
+
+/// Measures printed by an external command.
+pub struct Exec {
+    measure_types: Vec<SensorMeasureType>,
+    command: String,
+    args: Vec<String>,
+    timeout: Duration,
+}
+
+/// The command prints either a JSON object of measures
+/// (`{"temperature": 21.5, "humidity": 40}`) or a single number when only one
+/// measure is configured.
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(default)]
+pub struct ExecConfig {
+    pub command: String,
+    pub args: Vec<String>,
+    #[serde(with = "humantime_serde")]
+    pub timeout: Duration,
+    pub measures: Vec<SensorMeasureType>,
+}
+
+impl Default for ExecConfig {
+    fn default() -> Self {
+        Self {
+            command: String::new(),
+            args: vec![],
+            timeout: Duration::from_secs(10),
+            measures: vec![],
+        }
+    }
+}
+
+impl Exec {
+    pub fn new(config: &ExecConfig) -> Result<Self, SensorError> {
+        if config.command.is_empty() {
+            return Err(SensorError::Config("No command configured".into()));
+        }
+        if config.measures.is_empty() {
+            return Err(SensorError::Config("No measures configured".into()));
+        }
+
+        Ok(Self {
+            measure_types: config.measures.clone(),
+            command: config.command.clone(),
+            args: config.args.clone(),
+            timeout: config.timeout,
+        })
+    }
+
+    fn run(&self) -> Result<String, SensorError> {
+        let mut child = Command::new(&self.command)
+            .args(&self.args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        // read in threads so a verbose command can't block on a full pipe
+        let mut stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| SensorError::Io("No stdout".into()))?;
+        let stdout = thread::spawn(move || {
+            let mut string = String::new();
+            stdout.read_to_string(&mut string).map(|_| string)
+        });
+        let mut stderr = child
+            .stderr
+            .take()
+            .ok_or_else(|| SensorError::Io("No stderr".into()))?;
+        let stderr = thread::spawn(move || {
+            let mut string = String::new();
+            stderr.read_to_string(&mut string).map(|_| string)
+        });
+
+        let start = Instant::now();
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if start.elapsed() > self.timeout {
+                child.kill()?;
+                child.wait()?;
+                return Err(SensorError::Timeout(format!(
+                    "{} after {:?}",
+                    self.command, self.timeout
+                )));
+            }
+            thread::sleep(Duration::from_millis(10));
+        };
+
+        let panicked = |_| SensorError::Io("Output reader panicked".into());
+        let stdout = stdout.join().map_err(panicked)??;
+        let stderr = stderr.join().map_err(panicked)??;
+        if !status.success() {
+            return Err(SensorError::Io(format!(
+                "{} exited with {}: {}",
+                self.command,
+                status,
+                stderr.trim()
+            )));
+        }
+
+        Ok(stdout)
+    }
+
+    fn measure_from(
+        string: &str,
+        measure_types: &[SensorMeasureType],
+    ) -> Result<Measure, SensorError> {
+        let mut measure = Measure::default();
+        let string = string.trim();
+
+        if let Ok(value) = string.parse::<f32>() {
+            match measure_types {
+                [measure_type] => measure.set(measure_type, value),
+                _ => {
+                    return Err(SensorError::Parse(
+                        "Single value for more than one measure".into(),
+                    ))
+                }
+            }
+            return Ok(measure);
+        }
+
+        let values = serde_json::from_str::<serde_json::Map<_, _>>(string)?;
+        for measure_type in measure_types {
+            match values.get(measure_type.key()).map(|v| v.as_f64()) {
+                Some(Some(value)) => measure.set(measure_type, value as f32),
+                Some(None) => {
+                    return Err(SensorError::Parse(format!(
+                        "{} is not a number",
+                        measure_type.key()
+                    )))
+                }
+                None => debug!("{} not in output", measure_type.key()),
+            }
+        }
+
+        Ok(measure)
+    }
+}
+
+impl Sensor for Exec {
+    fn measure(&mut self) -> Result<Measure, SensorError> {
+        let string = self.run()?;
+
+        debug!("string: {string:?}");
+
+        Exec::measure_from(&string, &self.measure_types)
+    }
+
+    fn measure_types(&self) -> &Vec<SensorMeasureType> {
+        &self.measure_types
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{Measure, Sensor, SensorError, SensorMeasureType};
+
+    use super::{Exec, ExecConfig};
+
+    #[test]
+    fn exec_single_value() {
+        let measure =
+            Exec::measure_from("21.5\n", &[SensorMeasureType::Temperature])
+                .unwrap();
+
+        assert_eq!(
+            measure,
+            Measure::from([(SensorMeasureType::Temperature, 21.5)])
+        );
+        assert!(Exec::measure_from(
+            "21.5\n",
+            &[SensorMeasureType::Temperature, SensorMeasureType::Humidity]
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn exec_json_values() {
+        let measure = Exec::measure_from(
+            r#"{"temperature": 21.5, "humidity": 40, "other": 1}"#,
+            &[SensorMeasureType::Temperature, SensorMeasureType::Humidity],
+        )
+        .unwrap();
+
+        assert_eq!(
+            measure,
+            Measure::from([
+                (SensorMeasureType::Temperature, 21.5),
+                (SensorMeasureType::Humidity, 40.0)
+            ])
+        );
+        assert!(Exec::measure_from(
+            r#"{"temperature": "hot"}"#,
+            &[SensorMeasureType::Temperature]
+        )
+        .is_err());
+        assert!(Exec::measure_from("hot", &[SensorMeasureType::Temperature])
+            .is_err());
+    }
+
+    #[test]
+    fn exec_command_errors() {
+        let exec = |command: &str, timeout: u64| {
+            Exec::new(&ExecConfig {
+                command: "sh".into(),
+                args: vec!["-c".into(), command.into()],
+                timeout: Duration::from_millis(timeout),
+                measures: vec![SensorMeasureType::Temperature],
+            })
+            .unwrap()
+        };
+
+        assert_eq!(
+            exec("echo 12.5", 1000)
+                .measure()
+                .unwrap()
+                .get(&SensorMeasureType::Temperature),
+            Some(12.5)
+        );
+        assert!(matches!(
+            exec("exit 1", 1000).measure(),
+            Err(SensorError::Io(_))
+        ));
+        assert!(matches!(
+            exec("sleep 5", 100).measure(),
+            Err(SensorError::Timeout(_))
+        ));
+    }
+}
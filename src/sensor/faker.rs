@@ -1,10 +1,12 @@
 use rand::Rng;
 use serde::*;
-use std::error::Error;
 
-use crate::sensor::{Measure, SensorMeasureType};
+use crate::sensor::{round2, Measure, SensorError, SensorMeasureType};
 use crate::Sensor;
 
+/// Step, minimum and maximum of a random walk
+type Walk = (f32, f32, f32);
+
 pub struct Faker {
     measure: Measure,
     measure_types: Vec<SensorMeasureType>,
@@ -29,23 +31,21 @@ impl Default for FakerConfig {
 
 impl Faker {
     pub fn new(config: &FakerConfig) -> Self {
-        let mut measure = Measure {
-            ..Default::default()
-        };
+        let mut measure = Measure::default();
 
         let mut rng = rand::thread_rng();
 
         let mut measure_types = Vec::new();
 
-        config.measures.iter().for_each(|t| match t {
-            SensorMeasureType::Temperature => {
-                measure.temperature = Some(rng.gen_range(19.0..25.0));
-                measure_types.push(SensorMeasureType::Temperature);
-            }
-            SensorMeasureType::Humidity => {
-                measure.humidity = Some(rng.gen_range(40.0..60.0));
-                measure_types.push(SensorMeasureType::Humidity);
-            }
+        config.measures.iter().for_each(|t| {
+            let ((low, high), _) = Self::bounds(t);
+            let value = if low < high {
+                rng.gen_range(low..high)
+            } else {
+                low
+            };
+            measure.set(t, value);
+            measure_types.push(*t);
         });
 
         Faker {
@@ -53,24 +53,54 @@ impl Faker {
             measure_types,
         }
     }
+
+    /// Range of the first value, then the step and bounds of its random
+    /// walk if it moves at all
+    fn bounds(measure_type: &SensorMeasureType) -> ((f32, f32), Option<Walk>) {
+        match measure_type {
+            SensorMeasureType::Temperature => {
+                ((19.0, 25.0), Some((2.0, -20.0, 40.0)))
+            }
+            SensorMeasureType::Humidity => {
+                ((40.0, 60.0), Some((2.0, 0.0, 100.0)))
+            }
+            SensorMeasureType::Pressure => {
+                ((1000.0, 1025.0), Some((1.0, 950.0, 1050.0)))
+            }
+            SensorMeasureType::CpuTemperature => {
+                ((40.0, 60.0), Some((2.0, 30.0, 85.0)))
+            }
+            SensorMeasureType::Load1m
+            | SensorMeasureType::Load5m
+            | SensorMeasureType::Load15m => ((0.0, 2.0), None),
+            SensorMeasureType::MemoryUsage | SensorMeasureType::DiskUsage => {
+                ((10.0, 90.0), None)
+            }
+            SensorMeasureType::Co2 => {
+                ((400.0, 800.0), Some((20.0, 350.0, 2000.0)))
+            }
+            SensorMeasureType::Illuminance => {
+                ((100.0, 500.0), Some((20.0, 0.0, 1000.0)))
+            }
+            // flags and counters
+            _ => ((0.0, 0.0), None),
+        }
+    }
 }
 
 impl Sensor for Faker {
-    fn measure(&mut self) -> Result<Measure, Box<dyn Error>> {
+    fn measure(&mut self) -> Result<Measure, SensorError> {
         let mut rng = rand::thread_rng();
 
-        if let Some(ref mut h) = self.measure.humidity {
-            *h += rng.gen_range(-2.0..2.0);
-            *h = (*h).clamp(0.0, 100.0);
-            *h = (*h * 100.0).round() / 100.0; // round at 2 decimal
-        }
-        if let Some(ref mut t) = self.measure.temperature {
-            *t += rng.gen_range(-2.0..2.0);
-            *t = (*t).clamp(-20.0, 40.0);
-            *t = (*t * 100.0).round() / 100.0; // round at 2 decimal
+        for (t, v) in self.measure.iter_mut() {
+            if let (_, Some((step, min, max))) = Self::bounds(t) {
+                *v += rng.gen_range(-step..step);
+                *v = (*v).clamp(min, max);
+                *v = round2(*v);
+            }
         }
 
-        Ok(self.measure)
+        Ok(self.measure.clone())
     }
 
     fn measure_types(&self) -> &Vec<SensorMeasureType> {
@@ -0,0 +1,409 @@
+use serde::*;
+use std::collections::{HashMap, VecDeque};
+
+use crate::sensor::{round2, Measure, SensorError, SensorMeasureType};
+use crate::Sensor;
+
+/// Measures of another sensor smoothed, and spikes dropped, before they are
+/// published. Each measure type has its own chain of filters and state.
+pub struct Filtered {
+    sensor: Box<dyn Sensor>,
+    filters: HashMap<SensorMeasureType, Vec<Filter>>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterConfig {
+    /// Median of the last N values
+    Median(usize),
+    /// Mean of the last N values
+    Mean(usize),
+    /// Exponential moving average, weight of the new value in ]0, 1]
+    Ema(f32),
+    /// Drop a value changing more than this from the last one kept
+    RateOfChange(f32),
+    /// Drop a value more than `threshold` standard deviations away from the
+    /// mean of the last `window` values kept
+    ZScore { window: usize, threshold: f32 },
+}
+
+/// A filter with its state, `apply` returns the value to publish or `None`
+/// when it is dropped.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    Median(Window),
+    Mean(Window),
+    Ema { alpha: f32, value: Option<f32> },
+    RateOfChange(Spike, f32),
+    ZScore(Spike, Window, f32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Window {
+    size: usize,
+    values: VecDeque<f32>,
+}
+
+/// Spike filter state. After `Spike::MAX_REJECTED` values dropped in a row
+/// the level is considered to have really changed and the filter restarts
+/// from the next value.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Spike {
+    last: Option<f32>,
+    rejected: u32,
+}
+
+impl Window {
+    fn new(size: usize) -> Self {
+        Self {
+            size,
+            values: VecDeque::with_capacity(size),
+        }
+    }
+
+    fn push(&mut self, value: f32) {
+        if self.values.len() == self.size {
+            self.values.pop_front();
+        }
+        self.values.push_back(value);
+    }
+
+    fn is_full(&self) -> bool {
+        self.values.len() == self.size
+    }
+
+    fn mean(&self) -> f32 {
+        self.values.iter().sum::<f32>() / self.values.len() as f32
+    }
+
+    fn std_dev(&self) -> f32 {
+        let mean = self.mean();
+        let variance =
+            self.values.iter().map(|v| (v - mean).powi(2)).sum::<f32>()
+                / self.values.len() as f32;
+        variance.sqrt()
+    }
+
+    fn median(&self) -> f32 {
+        let mut values = self.values.iter().copied().collect::<Vec<_>>();
+        values.sort_by(f32::total_cmp);
+
+        let middle = values.len() / 2;
+        if values.len() % 2 == 0 {
+            (values[middle - 1] + values[middle]) / 2.0
+        } else {
+            values[middle]
+        }
+    }
+}
+
+impl Spike {
+    const MAX_REJECTED: u32 = 3;
+
+    /// `value` kept if `is_spike` is false for it
+    fn check(
+        &mut self,
+        value: f32,
+        is_spike: impl FnOnce(f32) -> bool,
+    ) -> Option<f32> {
+        if self.last.is_some()
+            && self.rejected < Self::MAX_REJECTED
+            && is_spike(value)
+        {
+            self.rejected += 1;
+            return None;
+        }
+
+        self.rejected = 0;
+        self.last = Some(value);
+        Some(value)
+    }
+}
+
+impl Filter {
+    pub fn new(config: &FilterConfig) -> Result<Self, SensorError> {
+        Ok(match *config {
+            FilterConfig::Median(0)
+            | FilterConfig::Mean(0)
+            | FilterConfig::ZScore { window: 0..=1, .. } => {
+                return Err(SensorError::Config(format!(
+                    "Window too small: {config:?}"
+                )))
+            }
+            FilterConfig::Ema(alpha) if !(alpha > 0.0 && alpha <= 1.0) => {
+                return Err(SensorError::Config(format!(
+                    "EMA weight not in ]0, 1]: {alpha}"
+                )))
+            }
+            FilterConfig::Median(size) => Filter::Median(Window::new(size)),
+            FilterConfig::Mean(size) => Filter::Mean(Window::new(size)),
+            FilterConfig::Ema(alpha) => Filter::Ema { alpha, value: None },
+            FilterConfig::RateOfChange(max) => {
+                Filter::RateOfChange(Spike::default(), max)
+            }
+            FilterConfig::ZScore { window, threshold } => {
+                Filter::ZScore(Spike::default(), Window::new(window), threshold)
+            }
+        })
+    }
+
+    pub fn apply(&mut self, value: f32) -> Option<f32> {
+        let value = match self {
+            Filter::Median(window) => {
+                window.push(value);
+                window.median()
+            }
+            Filter::Mean(window) => {
+                window.push(value);
+                window.mean()
+            }
+            Filter::Ema { alpha, value: last } => {
+                let ema = match last {
+                    Some(last) => *alpha * value + (1.0 - *alpha) * *last,
+                    None => value,
+                };
+                *last = Some(ema);
+                ema
+            }
+            Filter::RateOfChange(spike, max) => {
+                let last = spike.last;
+                spike.check(value, |v| {
+                    last.is_some_and(|last| (v - last).abs() > *max)
+                })?
+            }
+            Filter::ZScore(spike, window, threshold) => {
+                let (mean, std_dev) = (window.mean(), window.std_dev());
+                let full = window.is_full();
+                // a constant window, as quantized sensors give, has no
+                // deviation to compare a change with
+                let value = spike.check(value, |v| {
+                    full && std_dev > 0.0
+                        && (v - mean).abs() > *threshold * std_dev
+                });
+                match value {
+                    Some(value) => window.push(value),
+                    // restarts with the new level
+                    None if spike.rejected == Spike::MAX_REJECTED => {
+                        window.values.clear()
+                    }
+                    None => (),
+                }
+                value?
+            }
+        };
+
+        Some(round2(value))
+    }
+
+    pub fn apply_all(filters: &mut [Filter], value: f32) -> Option<f32> {
+        filters
+            .iter_mut()
+            .try_fold(value, |value, filter| filter.apply(value))
+    }
+}
+
+impl Filtered {
+    pub fn new(
+        sensor: Box<dyn Sensor>,
+        filters: &HashMap<SensorMeasureType, Vec<FilterConfig>>,
+    ) -> Result<Self, SensorError> {
+        if let Some(t) =
+            filters.keys().find(|t| !sensor.measure_types().contains(t))
+        {
+            return Err(SensorError::Config(format!(
+                "{} filtered but not measured",
+                t.key()
+            )));
+        }
+
+        let filters = filters
+            .iter()
+            .map(|(t, configs)| {
+                let filters = configs
+                    .iter()
+                    .map(Filter::new)
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok((*t, filters))
+            })
+            .collect::<Result<_, SensorError>>()?;
+
+        Ok(Self { sensor, filters })
+    }
+}
+
+impl Sensor for Filtered {
+    fn measure(&mut self) -> Result<Measure, SensorError> {
+        let mut measure = self.sensor.measure()?;
+
+        for (measure_type, filters) in &mut self.filters {
+            let Some(value) = measure.get(measure_type) else {
+                continue;
+            };
+            match Filter::apply_all(filters, value) {
+                Some(value) => measure.set(measure_type, value),
+                None => {
+                    measure.remove(measure_type);
+                }
+            }
+        }
+
+        Ok(measure)
+    }
+
+    fn measure_types(&self) -> &Vec<SensorMeasureType> {
+        self.sensor.measure_types()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{Faker, FakerConfig, SensorError, SensorMeasureType};
+
+    use super::{Filter, FilterConfig, Filtered};
+
+    fn filter(config: FilterConfig) -> Filter {
+        Filter::new(&config).unwrap()
+    }
+
+    fn apply(filter: &mut Filter, values: &[f32]) -> Vec<Option<f32>> {
+        values.iter().map(|v| filter.apply(*v)).collect()
+    }
+
+    #[test]
+    fn filter_config() {
+        assert!(Filter::new(&FilterConfig::Median(0)).is_err());
+        assert!(Filter::new(&FilterConfig::Ema(0.0)).is_err());
+        assert!(Filter::new(&FilterConfig::Ema(1.5)).is_err());
+        assert!(Filter::new(&FilterConfig::ZScore {
+            window: 1,
+            threshold: 3.0
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn filter_median() {
+        let mut median = filter(FilterConfig::Median(3));
+
+        assert_eq!(
+            apply(&mut median, &[20.0, 85.0, 21.0, 22.0, 21.5]),
+            vec![Some(20.0), Some(52.5), Some(21.0), Some(22.0), Some(21.5)]
+        );
+    }
+
+    #[test]
+    fn filter_mean() {
+        let mut mean = filter(FilterConfig::Mean(2));
+
+        assert_eq!(
+            apply(&mut mean, &[20.0, 21.0, 23.0]),
+            vec![Some(20.0), Some(20.5), Some(22.0)]
+        );
+    }
+
+    #[test]
+    fn filter_ema() {
+        let mut ema = filter(FilterConfig::Ema(0.25));
+
+        assert_eq!(
+            apply(&mut ema, &[20.0, 24.0, 24.0]),
+            vec![Some(20.0), Some(21.0), Some(21.75)]
+        );
+    }
+
+    #[test]
+    fn filter_rate_of_change() {
+        let mut rate = filter(FilterConfig::RateOfChange(2.0));
+
+        assert_eq!(
+            apply(&mut rate, &[20.0, 21.5, 85.0, 22.0, 30.0, 30.0, 30.0, 30.0]),
+            vec![
+                Some(20.0),
+                Some(21.5),
+                None,
+                Some(22.0),
+                // a real change, kept after 3 values dropped
+                None,
+                None,
+                None,
+                Some(30.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn filter_z_score() {
+        let mut z_score = filter(FilterConfig::ZScore {
+            window: 4,
+            threshold: 3.0,
+        });
+
+        assert_eq!(
+            apply(&mut z_score, &[20.0, 20.5, 20.0, 20.5, 85.0, 20.25, 20.75]),
+            vec![
+                Some(20.0),
+                Some(20.5),
+                Some(20.0),
+                Some(20.5),
+                None,
+                Some(20.25),
+                Some(20.75)
+            ]
+        );
+
+        // a step from a constant window is kept
+        let mut z_score = filter(FilterConfig::ZScore {
+            window: 4,
+            threshold: 3.0,
+        });
+        assert_eq!(
+            apply(&mut z_score, &[20.0, 20.0, 20.0, 20.0, 20.0625, 20.0625]),
+            vec![
+                Some(20.0),
+                Some(20.0),
+                Some(20.0),
+                Some(20.0),
+                Some(20.06),
+                Some(20.06)
+            ]
+        );
+    }
+
+    #[test]
+    fn filter_chain() {
+        let mut filters = vec![
+            filter(FilterConfig::RateOfChange(5.0)),
+            filter(FilterConfig::Mean(2)),
+        ];
+
+        let values = [20.0, 85.0, 22.0]
+            .iter()
+            .map(|v| Filter::apply_all(&mut filters, *v))
+            .collect::<Vec<_>>();
+        assert_eq!(values, vec![Some(20.0), None, Some(21.0)]);
+    }
+
+    #[test]
+    fn filtered_sensor() {
+        let faker = || {
+            Box::new(Faker::new(&FakerConfig {
+                measures: vec![SensorMeasureType::Temperature],
+            }))
+        };
+        let filters = |measure_type| {
+            HashMap::from([(measure_type, vec![FilterConfig::Median(3)])])
+        };
+
+        assert!(Filtered::new(
+            faker(),
+            &filters(SensorMeasureType::Temperature)
+        )
+        .is_ok());
+        assert!(matches!(
+            Filtered::new(faker(), &filters(SensorMeasureType::Humidity)),
+            Err(SensorError::Config(_))
+        ));
+    }
+}
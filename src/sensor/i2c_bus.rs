@@ -0,0 +1,52 @@
+use rppal::i2c::I2c;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+
+use crate::sensor::SensorError;
+
+/// Handle on an I2C bus shared by every sensor configured on it.
+///
+/// The bus is opened once, sensors lock it for the whole transaction so two
+/// devices never talk on the wire at the same time.
+#[derive(Clone)]
+pub struct I2cBus {
+    i2c: Arc<Mutex<I2c>>,
+}
+
+impl I2cBus {
+    /// Get the handle of `/dev/i2c-<bus>`, opening it the first time.
+    pub fn get(bus: u8) -> Result<Self, SensorError> {
+        static BUSES: OnceLock<Mutex<HashMap<u8, Arc<Mutex<I2c>>>>> =
+            OnceLock::new();
+
+        let mut buses = BUSES
+            .get_or_init(Default::default)
+            .lock()
+            .map_err(|e| SensorError::Io(e.to_string()))?;
+
+        let i2c = match buses.get(&bus) {
+            Some(i2c) => i2c.clone(),
+            None => {
+                let i2c = Arc::new(Mutex::new(I2c::with_bus(bus)?));
+                buses.insert(bus, i2c.clone());
+                i2c
+            }
+        };
+
+        Ok(Self { i2c })
+    }
+
+    /// Lock the bus and address the device at `address`.
+    pub fn lock(
+        &self,
+        address: u16,
+    ) -> Result<MutexGuard<'_, I2c>, SensorError> {
+        let mut i2c = self
+            .i2c
+            .lock()
+            .map_err(|e| SensorError::Io(e.to_string()))?;
+        i2c.set_slave_address(address)?;
+
+        Ok(i2c)
+    }
+}
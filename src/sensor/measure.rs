@@ -1,10 +1,164 @@
 use serde::Serialize;
+use std::collections::BTreeMap;
+use std::time::{Duration, SystemTime};
 
-#[derive(Debug, PartialEq, Serialize, Default, Clone, Copy)]
+use crate::sensor::SensorMeasureType;
+
+pub(crate) fn round2(value: impl Into<f64>) -> f32 {
+    ((value.into() * 100.0).round() / 100.0) as f32
+}
+
+/// Values of a reading keyed by quantity, serialized as a flat object
+/// (`{"temperature": 21.1, "humidity": 61.8}`). The metadata is left to the
+/// publishers, see `Payload`.
+#[derive(Debug, PartialEq, Serialize, Default, Clone)]
 pub struct Measure {
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub temperature: Option<f32>,
+    #[serde(flatten)]
+    values: BTreeMap<SensorMeasureType, f32>,
+    #[serde(skip)]
+    pub metadata: MeasureMetadata,
+}
+
+/// How and when a reading was taken.
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+pub struct MeasureMetadata {
+    /// When the sample was taken, set once the sensor returned it
+    pub time: Option<SystemTime>,
+    /// Time spent in `Sensor::measure`
+    pub read_duration: Option<Duration>,
+    /// Reads attempted again by the sensor before this one succeeded
+    pub retries: u32,
+}
+
+impl Measure {
+    pub fn set(&mut self, measure_type: &SensorMeasureType, value: f32) {
+        self.values.insert(*measure_type, value);
+    }
+
+    pub fn remove(&mut self, measure_type: &SensorMeasureType) -> Option<f32> {
+        self.values.remove(measure_type)
+    }
+
+    pub fn get(&self, measure_type: &SensorMeasureType) -> Option<f32> {
+        self.values.get(measure_type).copied()
+    }
+
+    pub fn get_mut(
+        &mut self,
+        measure_type: &SensorMeasureType,
+    ) -> Option<&mut f32> {
+        self.values.get_mut(measure_type)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&SensorMeasureType, &f32)> {
+        self.values.iter()
+    }
+
+    pub fn iter_mut(
+        &mut self,
+    ) -> impl Iterator<Item = (&SensorMeasureType, &mut f32)> {
+        self.values.iter_mut()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+impl<const N: usize> From<[(SensorMeasureType, f32); N]> for Measure {
+    fn from(values: [(SensorMeasureType, f32); N]) -> Self {
+        Self {
+            values: BTreeMap::from(values),
+            ..Default::default()
+        }
+    }
+}
+
+/// What a measure type is, everything a publisher needs to describe it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quantity {
+    /// Name of the measure field in payloads
+    pub key: &'static str,
+    /// Human name, of the Home Assistant entity
+    pub name: &'static str,
+    pub unit: Option<&'static str>,
+    pub device_class: Option<&'static str>,
+    /// Home Assistant state class, `measurement` or `total_increasing`
+    pub state_class: &'static str,
+    /// About the host running the sensors rather than its environment
+    pub diagnostic: bool,
+}
+
+impl Quantity {
+    pub const fn measurement(
+        key: &'static str,
+        name: &'static str,
+        unit: Option<&'static str>,
+        device_class: Option<&'static str>,
+    ) -> Self {
+        Self {
+            key,
+            name,
+            unit,
+            device_class,
+            state_class: "measurement",
+            diagnostic: false,
+        }
+    }
+
+    /// A counter only reset when the host restarts
+    pub const fn total_increasing(
+        key: &'static str,
+        name: &'static str,
+        unit: Option<&'static str>,
+        device_class: Option<&'static str>,
+    ) -> Self {
+        Self {
+            state_class: "total_increasing",
+            ..Self::measurement(key, name, unit, device_class)
+        }
+    }
+
+    pub const fn diagnostic(self) -> Self {
+        Self {
+            diagnostic: true,
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::Measure;
+    use crate::SensorMeasureType;
+
+    #[test]
+    fn measure_serialize() {
+        let measure = Measure::from([
+            (SensorMeasureType::Humidity, 61.8),
+            (SensorMeasureType::Temperature, 21.1),
+        ]);
+
+        assert_eq!(
+            serde_json::to_string(&measure).unwrap(),
+            r#"{"temperature":21.1,"humidity":61.8}"#
+        );
+        assert_eq!(serde_json::to_string(&Measure::default()).unwrap(), "{}");
+    }
+
+    #[test]
+    fn measure_serialize_without_metadata() {
+        let mut measure =
+            Measure::from([(SensorMeasureType::Pressure, 1013.2)]);
+        measure.metadata.time = Some(SystemTime::UNIX_EPOCH);
+        measure.metadata.read_duration = Some(Duration::from_millis(12));
+        measure.metadata.retries = 2;
 
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub humidity: Option<f32>,
+        assert_eq!(
+            serde_json::to_string(&measure).unwrap(),
+            r#"{"pressure":1013.2}"#
+        );
+    }
 }
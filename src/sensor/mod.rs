@@ -1,47 +1,436 @@
 use serde::{Deserialize, Serialize};
-use std::error::Error;
+use std::collections::HashMap;
 use std::fmt::Display;
 
-use crate::config::ConfigSensor;
+use crate::config::{ConfigSensor, ConfigSensorEntry, ConfigSensorOptions};
 
 pub use self::am2320::{AM2320Config, AM2320};
+pub use self::bme280::{Bme280, Bme280Config};
+pub use self::calibration::{Calibrated, CalibrationConfig};
+pub use self::dead_band::{DeadBand, MinChange};
+pub use self::derived::Derived;
+pub use self::dht22::{Dht22, Dht22Config, DhtModel};
 pub use self::ds18b20::{Ds18b20, Ds18b20Config};
+pub use self::error::SensorError;
+pub use self::exec::{Exec, ExecConfig};
 pub use self::faker::{Faker, FakerConfig};
-pub use self::measure::Measure;
+pub use self::filter::{Filter, FilterConfig, Filtered};
+pub use self::i2c_bus::I2cBus;
+pub(crate) use self::measure::round2;
+pub use self::measure::{Measure, MeasureMetadata, Quantity};
+pub use self::oversampling::{
+    Aggregate, Oversampled, Oversampling, OversamplingConfig,
+};
+pub use self::retry::{Retry, RetryConfig};
+pub use self::sht::{Sht, ShtConfig, ShtModel, ShtPrecision};
+pub use self::sysfs::{Sysfs, SysfsChannelConfig, SysfsConfig};
+pub use self::system::{System, SystemConfig, SystemDisk};
+pub use self::thermal_zone::{ThermalZone, ThermalZoneConfig};
 
 mod am2320;
+mod bme280;
+mod calibration;
+mod dead_band;
+mod derived;
+mod dht22;
 pub mod ds18b20;
+mod error;
+mod exec;
 mod faker;
+mod filter;
+mod i2c_bus;
 mod measure;
+mod oversampling;
+mod retry;
+mod sht;
+mod sysfs;
+mod system;
+mod thermal_zone;
 
-pub trait Sensor {
-    fn measure(&mut self) -> Result<Measure, Box<dyn Error>>;
+/// `Send` so each sensor can be read on a blocking thread of its own.
+pub trait Sensor: Send {
+    fn measure(&mut self) -> Result<Measure, SensorError>;
     fn measure_types(&self) -> &Vec<SensorMeasureType>;
 }
 
+pub type NamedSensors = Vec<(String, Box<dyn Sensor>)>;
+
 impl dyn Sensor {
-    pub fn new(config: &ConfigSensor) -> Box<dyn Sensor> {
+    pub fn new(config: &ConfigSensor) -> Result<Box<dyn Sensor>, SensorError> {
+        Ok(match config {
+            ConfigSensor::AM2320(cfg) => Box::new(AM2320::new(cfg)?),
+            ConfigSensor::Bme280(cfg) => Box::new(Bme280::new(cfg)?),
+            ConfigSensor::Sht(cfg) => Box::new(Sht::new(cfg)?),
+            ConfigSensor::Dht22(cfg) => Box::new(Dht22::new(cfg)?),
+            ConfigSensor::ThermalZone(cfg) => Box::new(ThermalZone::new(cfg)?),
+            ConfigSensor::Sysfs(cfg) => Box::new(Sysfs::new(cfg)?),
+            ConfigSensor::System(cfg) => Box::new(System::new(cfg)),
+            ConfigSensor::Exec(cfg) => Box::new(Exec::new(cfg)?),
+            ConfigSensor::Faker(cfg) => Box::new(Faker::new(cfg)),
+            ConfigSensor::Ds18b20(cfg) => Box::new(Ds18b20::new(cfg)?),
+        })
+    }
+
+    /// Sensors of a config entry with their ids and options applied, one
+    /// entry can produce many sensors when they are discovered.
+    pub fn new_all(
+        sensor_id: &str,
+        config: &ConfigSensorEntry,
+    ) -> Result<NamedSensors, SensorError> {
+        let sensors = <dyn Sensor>::new_sensors(sensor_id, &config.sensor)?;
+
+        let measure_types = sensors
+            .iter()
+            .flat_map(|(_, sensor)| sensor.measure_types().clone())
+            .collect::<Vec<_>>();
+        let options = &config.options;
+        for (t, option) in options
+            .calibration
+            .keys()
+            .map(|t| (t, "calibrated"))
+            .chain(options.filters.keys().map(|t| (t, "filtered")))
+        {
+            if !measure_types.contains(t) {
+                return Err(SensorError::Config(format!(
+                    "{} {option} but not measured by {sensor_id}",
+                    t.key()
+                )));
+            }
+        }
+
+        sensors
+            .into_iter()
+            .map(|(id, sensor)| {
+                Ok((id, <dyn Sensor>::with_options(sensor, &config.options)?))
+            })
+            .collect()
+    }
+
+    /// Calibration first and filters next so derived quantities use the
+    /// corrected values
+    fn with_options(
+        mut sensor: Box<dyn Sensor>,
+        options: &ConfigSensorOptions,
+    ) -> Result<Box<dyn Sensor>, SensorError> {
+        let calibration =
+            Self::own(&options.calibration, sensor.measure_types());
+        if !calibration.is_empty() {
+            sensor = Box::new(Calibrated::new(sensor, &calibration)?);
+        }
+        let filters = Self::own(&options.filters, sensor.measure_types());
+        if !filters.is_empty() {
+            sensor = Box::new(Filtered::new(sensor, &filters)?);
+        }
+        if !options.derived.is_empty() {
+            sensor = Box::new(Derived::new(sensor, &options.derived)?);
+        }
+        Ok(sensor)
+    }
+
+    /// Options of the measures of a sensor, an entry expanded in many
+    /// sensors shares its options.
+    fn own<T: Clone>(
+        options: &HashMap<SensorMeasureType, T>,
+        measure_types: &[SensorMeasureType],
+    ) -> HashMap<SensorMeasureType, T> {
+        options
+            .iter()
+            .filter(|(t, _)| measure_types.contains(t))
+            .map(|(t, option)| (*t, option.clone()))
+            .collect()
+    }
+
+    /// Ids of the sensors `new_all` creates for a config entry, as far as
+    /// they can be known before: discovered probes on the bus or named.
+    pub fn sensor_ids(sensor_id: &str, config: &ConfigSensor) -> Vec<String> {
         match config {
-            ConfigSensor::AM2320(_cfg) => {
-                Box::new(AM2320::new().expect("I2C is enabled ?"))
+            ConfigSensor::Ds18b20(cfg) if cfg.discover => {
+                Ds18b20::sensor_ids(sensor_id, cfg)
             }
-            ConfigSensor::Faker(cfg) => Box::new(Faker::new(cfg)),
-            ConfigSensor::Ds18b20(cfg) => {
-                Box::new(Ds18b20::new(cfg).expect("1-Wire is enabled ?"))
+            ConfigSensor::System(cfg) => System::sensor_ids(sensor_id, cfg),
+            _ => vec![sensor_id.into()],
+        }
+    }
+
+    fn new_sensors(
+        sensor_id: &str,
+        config: &ConfigSensor,
+    ) -> Result<NamedSensors, SensorError> {
+        match config {
+            ConfigSensor::Ds18b20(cfg) if cfg.discover => {
+                Ok(Ds18b20::discover(sensor_id, cfg)?
+                    .into_iter()
+                    .map(|(id, s)| (id, Box::new(s) as Box<dyn Sensor>))
+                    .collect())
             }
+            ConfigSensor::System(cfg) => System::new_all(sensor_id, cfg),
+            _ => Ok(vec![(sensor_id.into(), <dyn Sensor>::new(config)?)]),
         }
     }
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[derive(
+    Deserialize,
+    Serialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+)]
 #[serde(rename_all = "snake_case")]
 pub enum SensorMeasureType {
     Temperature,
     Humidity,
+    Pressure,
+    Co2,
+    Illuminance,
+    Voltage,
+    Power,
+    DewPoint,
+    AbsoluteHumidity,
+    HeatIndex,
+    Humidex,
+    CpuTemperature,
+    UnderVoltage,
+    Throttled,
+    #[serde(rename = "load_1m")]
+    Load1m,
+    #[serde(rename = "load_5m")]
+    Load5m,
+    #[serde(rename = "load_15m")]
+    Load15m,
+    MemoryUsage,
+    DiskUsage,
+    Uptime,
+    NetworkReceived,
+    NetworkTransmitted,
+}
+
+impl SensorMeasureType {
+    pub fn key(&self) -> &'static str {
+        self.quantity().key
+    }
+
+    /// Declaring a new quantity only takes a variant and its line here
+    pub fn quantity(&self) -> Quantity {
+        use SensorMeasureType::*;
+
+        match self {
+            Temperature => Quantity::measurement(
+                "temperature",
+                "Temperature",
+                Some("°C"),
+                Some("temperature"),
+            ),
+            Humidity => Quantity::measurement(
+                "humidity",
+                "Humidity",
+                Some("%"),
+                Some("humidity"),
+            ),
+            Pressure => Quantity::measurement(
+                "pressure",
+                "Pressure",
+                Some("hPa"),
+                Some("pressure"),
+            ),
+            Co2 => Quantity::measurement(
+                "co2",
+                "CO2",
+                Some("ppm"),
+                Some("carbon_dioxide"),
+            ),
+            Illuminance => Quantity::measurement(
+                "illuminance",
+                "Illuminance",
+                Some("lx"),
+                Some("illuminance"),
+            ),
+            Voltage => Quantity::measurement(
+                "voltage",
+                "Voltage",
+                Some("V"),
+                Some("voltage"),
+            ),
+            Power => Quantity::measurement(
+                "power",
+                "Power",
+                Some("W"),
+                Some("power"),
+            ),
+            DewPoint => Quantity::measurement(
+                "dew_point",
+                "Dew point",
+                Some("°C"),
+                Some("temperature"),
+            ),
+            AbsoluteHumidity => Quantity::measurement(
+                "absolute_humidity",
+                "Absolute humidity",
+                Some("g/m³"),
+                None,
+            ),
+            HeatIndex => Quantity::measurement(
+                "heat_index",
+                "Heat index",
+                Some("°C"),
+                Some("temperature"),
+            ),
+            Humidex => Quantity::measurement(
+                "humidex",
+                "Humidex",
+                Some("°C"),
+                Some("temperature"),
+            ),
+            CpuTemperature => Quantity::measurement(
+                "cpu_temperature",
+                "CPU temperature",
+                Some("°C"),
+                Some("temperature"),
+            )
+            .diagnostic(),
+            UnderVoltage => Quantity::measurement(
+                "under_voltage",
+                "Under-voltage",
+                None,
+                None,
+            )
+            .diagnostic(),
+            Throttled => {
+                Quantity::measurement("throttled", "Throttled", None, None)
+                    .diagnostic()
+            }
+            Load1m => Quantity::measurement("load_1m", "Load 1m", None, None)
+                .diagnostic(),
+            Load5m => Quantity::measurement("load_5m", "Load 5m", None, None)
+                .diagnostic(),
+            Load15m => {
+                Quantity::measurement("load_15m", "Load 15m", None, None)
+                    .diagnostic()
+            }
+            MemoryUsage => Quantity::measurement(
+                "memory_usage",
+                "Memory usage",
+                Some("%"),
+                None,
+            )
+            .diagnostic(),
+            DiskUsage => Quantity::measurement(
+                "disk_usage",
+                "Disk usage",
+                Some("%"),
+                None,
+            )
+            .diagnostic(),
+            Uptime => Quantity::total_increasing(
+                "uptime",
+                "Uptime",
+                Some("h"),
+                Some("duration"),
+            )
+            .diagnostic(),
+            NetworkReceived => Quantity::total_increasing(
+                "network_received",
+                "Network received",
+                Some("GiB"),
+                Some("data_size"),
+            )
+            .diagnostic(),
+            NetworkTransmitted => Quantity::total_increasing(
+                "network_transmitted",
+                "Network transmitted",
+                Some("GiB"),
+                Some("data_size"),
+            )
+            .diagnostic(),
+        }
+    }
 }
 
 impl Display for SensorMeasureType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self)
+        write!(f, "{}", self.quantity().name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ConfigSensorEntry, Sensor, SensorError};
+
+    use super::SensorMeasureType::{self, *};
+
+    const ALL: [SensorMeasureType; 22] = [
+        Temperature,
+        Humidity,
+        Pressure,
+        Co2,
+        Illuminance,
+        Voltage,
+        Power,
+        DewPoint,
+        AbsoluteHumidity,
+        HeatIndex,
+        Humidex,
+        CpuTemperature,
+        UnderVoltage,
+        Throttled,
+        Load1m,
+        Load5m,
+        Load15m,
+        MemoryUsage,
+        DiskUsage,
+        Uptime,
+        NetworkReceived,
+        NetworkTransmitted,
+    ];
+
+    #[test]
+    fn sensor_measure_type_key() {
+        for measure_type in ALL {
+            assert_eq!(
+                serde_json::to_value(measure_type).unwrap(),
+                measure_type.key()
+            );
+            assert_eq!(
+                serde_yaml::from_str::<SensorMeasureType>(measure_type.key())
+                    .unwrap(),
+                measure_type
+            );
+        }
+    }
+
+    #[test]
+    fn sensor_new_all_options() {
+        let entry = |options: &str| {
+            serde_yaml::from_str::<ConfigSensorEntry>(&format!(
+                "faker:\n  measures: [temperature]\n{options}"
+            ))
+            .unwrap()
+        };
+
+        let sensors = <dyn Sensor>::new_all(
+            "sensor-1",
+            &entry("filters:\n  temperature: [median: 3]"),
+        )
+        .unwrap();
+        assert_eq!(sensors.len(), 1);
+        assert!(matches!(
+            <dyn Sensor>::new_all(
+                "sensor-1",
+                &entry("filters:\n  humidity: [median: 3]"),
+            ),
+            Err(SensorError::Config(_))
+        ));
+        assert!(matches!(
+            <dyn Sensor>::new_all(
+                "sensor-1",
+                &entry("calibration:\n  humidity: { offset: 2 }"),
+            ),
+            Err(SensorError::Config(_))
+        ));
     }
 }
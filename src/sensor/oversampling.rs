@@ -0,0 +1,214 @@
+use serde::*;
+use std::time::Duration;
+
+use crate::sensor::{round2, Measure};
+
+/// `samples` reads spread over the interval, published as one aggregate.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct OversamplingConfig {
+    pub samples: u32,
+    pub aggregate: Aggregate,
+    /// Also publish the minimum and maximum as `<id>-min` and `<id>-max`
+    pub min_max: bool,
+}
+
+impl Default for OversamplingConfig {
+    fn default() -> Self {
+        Self {
+            samples: 1,
+            aggregate: Aggregate::default(),
+            min_max: false,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Aggregate {
+    Min,
+    Max,
+    #[default]
+    Mean,
+    Last,
+}
+
+/// Samples of a sensor taken since the last publish.
+pub struct Oversampling {
+    config: OversamplingConfig,
+    slots: u32,
+    samples: Vec<Measure>,
+}
+
+/// What to publish once all the samples of an interval are taken.
+#[derive(Debug, PartialEq)]
+pub struct Oversampled {
+    pub measure: Measure,
+    pub min_max: Option<(Measure, Measure)>,
+}
+
+impl Oversampling {
+    pub fn new(config: &OversamplingConfig) -> Self {
+        Self {
+            config: OversamplingConfig {
+                samples: config.samples.max(1),
+                ..config.clone()
+            },
+            slots: 0,
+            samples: vec![],
+        }
+    }
+
+    /// Ids a sensor publishes to, with the `-min` and `-max` ones
+    pub fn sensor_ids(&self, sensor_id: &str) -> Vec<String> {
+        let mut sensor_ids = vec![sensor_id.to_string()];
+        if self.config.min_max {
+            sensor_ids.push(format!("{sensor_id}-min"));
+            sensor_ids.push(format!("{sensor_id}-max"));
+        }
+        sensor_ids
+    }
+
+    pub fn period(&self, interval: Duration) -> Duration {
+        interval / self.config.samples
+    }
+
+    /// The sample of a slot, `None` when the read failed. Returns the
+    /// aggregates once every slot of the interval is done, if any read
+    /// succeeded.
+    pub fn push(&mut self, measure: Option<Measure>) -> Option<Oversampled> {
+        self.slots += 1;
+        self.samples.extend(measure);
+        if self.slots < self.config.samples {
+            return None;
+        }
+
+        self.slots = 0;
+        let samples = std::mem::take(&mut self.samples);
+        let last = samples.last()?;
+
+        let aggregate = |aggregate| {
+            let mut measure = Self::aggregate(&samples, aggregate);
+            measure.metadata = last.metadata;
+            measure.metadata.retries =
+                samples.iter().map(|m| m.metadata.retries).sum();
+            measure
+        };
+
+        Some(Oversampled {
+            measure: aggregate(self.config.aggregate),
+            min_max: self.config.min_max.then(|| {
+                (aggregate(Aggregate::Min), aggregate(Aggregate::Max))
+            }),
+        })
+    }
+
+    /// Each measure type aggregated over the samples where it is present
+    fn aggregate(samples: &[Measure], aggregate: Aggregate) -> Measure {
+        let mut measure = Measure::default();
+
+        for sample in samples {
+            for (measure_type, value) in sample.iter() {
+                let value = match (aggregate, measure.get(measure_type)) {
+                    (_, None) | (Aggregate::Last, _) => *value,
+                    (Aggregate::Min, Some(min)) => min.min(*value),
+                    (Aggregate::Max, Some(max)) => max.max(*value),
+                    // summed here, divided below
+                    (Aggregate::Mean, Some(sum)) => sum + value,
+                };
+                measure.set(measure_type, value);
+            }
+        }
+
+        if aggregate == Aggregate::Mean {
+            for (measure_type, sum) in measure.iter_mut() {
+                let count =
+                    samples.iter().filter(|m| m.get(measure_type).is_some());
+                let mean = *sum / count.count() as f32;
+                *sum = round2(mean);
+            }
+        }
+
+        measure
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{Measure, SensorMeasureType};
+
+    use super::{Aggregate, Oversampled, Oversampling, OversamplingConfig};
+
+    fn sample(temperature: f32, humidity: Option<f32>) -> Measure {
+        let mut measure =
+            Measure::from([(SensorMeasureType::Temperature, temperature)]);
+        if let Some(humidity) = humidity {
+            measure.set(&SensorMeasureType::Humidity, humidity);
+        }
+        measure
+    }
+
+    #[test]
+    fn oversampling_aggregates() {
+        let samples = [
+            sample(20.0, Some(40.0)),
+            sample(23.0, None),
+            sample(21.0, Some(50.0)),
+        ];
+        let aggregate = |a| Oversampling::aggregate(&samples, a);
+
+        assert_eq!(aggregate(Aggregate::Min), sample(20.0, Some(40.0)));
+        assert_eq!(aggregate(Aggregate::Max), sample(23.0, Some(50.0)));
+        assert_eq!(aggregate(Aggregate::Mean), sample(21.33, Some(45.0)));
+        assert_eq!(aggregate(Aggregate::Last), sample(21.0, Some(50.0)));
+    }
+
+    #[test]
+    fn oversampling_push() {
+        let mut oversampling = Oversampling::new(&OversamplingConfig {
+            samples: 3,
+            aggregate: Aggregate::Max,
+            min_max: true,
+        });
+        assert_eq!(
+            oversampling.period(Duration::from_secs(480)),
+            Duration::from_secs(160)
+        );
+        assert_eq!(
+            oversampling.sensor_ids("room"),
+            vec!["room", "room-min", "room-max"]
+        );
+
+        assert_eq!(oversampling.push(Some(sample(20.0, None))), None);
+        // failed read
+        assert_eq!(oversampling.push(None), None);
+        assert_eq!(
+            oversampling.push(Some(sample(22.0, None))),
+            Some(Oversampled {
+                measure: sample(22.0, None),
+                min_max: Some((sample(20.0, None), sample(22.0, None)))
+            })
+        );
+
+        // every read of the interval failed
+        assert_eq!(oversampling.push(None), None);
+        assert_eq!(oversampling.push(None), None);
+        assert_eq!(oversampling.push(None), None);
+    }
+
+    #[test]
+    fn oversampling_default() {
+        let mut oversampling =
+            Oversampling::new(&OversamplingConfig::default());
+
+        assert_eq!(
+            oversampling.push(Some(sample(20.0, Some(40.0)))),
+            Some(Oversampled {
+                measure: sample(20.0, Some(40.0)),
+                min_max: None
+            })
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use serde::*;
+use std::time::Duration;
+
+use crate::sensor::SensorError;
+
+/// What to do when reads of a sensor fail.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct RetryConfig {
+    /// Retries of a failed read right away
+    pub retries: u32,
+    /// Delay before the first retry, doubled for each next one
+    #[serde(with = "humantime_serde")]
+    pub backoff: Duration,
+    /// Failed reads in a row before the sensor is unavailable
+    pub max_failures: u32,
+    /// Time between two reads of an unavailable sensor, until one succeeds
+    #[serde(with = "humantime_serde")]
+    pub probe_interval: Duration,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            retries: 2,
+            backoff: Duration::from_millis(500),
+            max_failures: 3,
+            probe_interval: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Failed reads of a sensor in a row, the sensor is unavailable after
+/// `max_failures` of them and only probed until a read succeeds.
+pub struct Retry {
+    config: RetryConfig,
+    failures: u32,
+}
+
+impl Retry {
+    pub fn new(config: &RetryConfig) -> Self {
+        Self {
+            config: RetryConfig {
+                max_failures: config.max_failures.max(1),
+                ..config.clone()
+            },
+            failures: 0,
+        }
+    }
+
+    pub fn available(&self) -> bool {
+        self.failures < self.config.max_failures
+    }
+
+    pub fn probe_interval(&self) -> Duration {
+        self.config.probe_interval
+    }
+
+    /// Delay before retrying a read failed with `err` after `retries`
+    /// retries already, `None` to give up. Only transient errors are retried
+    /// and a probe is not.
+    pub fn delay(&self, retries: u32, err: &SensorError) -> Option<Duration> {
+        let transient = matches!(
+            err,
+            SensorError::Io(_) | SensorError::Crc(_) | SensorError::Timeout(_)
+        );
+        if retries >= self.config.retries || !self.available() || !transient {
+            return None;
+        }
+        Some(self.config.backoff * 2u32.saturating_pow(retries))
+    }
+
+    /// Count a read, retries included, failed with `err` if any. A missing
+    /// device is unavailable right away. Returns the new availability when
+    /// it changed.
+    pub fn read(&mut self, err: Option<&SensorError>) -> Option<bool> {
+        let available = self.available();
+        self.failures = match err {
+            None => 0,
+            Some(SensorError::Missing(_)) => self.config.max_failures,
+            Some(_) => self.failures.saturating_add(1),
+        };
+        (self.available() != available).then_some(self.available())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::SensorError;
+
+    use super::{Retry, RetryConfig};
+
+    #[test]
+    fn retry_delay() {
+        let retry = Retry::new(&RetryConfig {
+            retries: 3,
+            backoff: Duration::from_millis(100),
+            ..Default::default()
+        });
+        let crc = SensorError::Crc("0x12".into());
+
+        assert_eq!(retry.delay(0, &crc), Some(Duration::from_millis(100)));
+        assert_eq!(retry.delay(1, &crc), Some(Duration::from_millis(200)));
+        assert_eq!(retry.delay(2, &crc), Some(Duration::from_millis(400)));
+        assert_eq!(retry.delay(3, &crc), None);
+
+        let delay = |err| retry.delay(0, &err);
+        assert!(delay(SensorError::Io("".into())).is_some());
+        assert!(delay(SensorError::Timeout("".into())).is_some());
+        assert_eq!(delay(SensorError::Parse("".into())), None);
+        assert_eq!(delay(SensorError::Missing("".into())), None);
+        assert_eq!(delay(SensorError::Config("".into())), None);
+    }
+
+    #[test]
+    fn retry_availability() {
+        let mut retry = Retry::new(&RetryConfig {
+            max_failures: 2,
+            ..Default::default()
+        });
+        let crc = SensorError::Crc("0x12".into());
+
+        assert_eq!(retry.read(Some(&crc)), None);
+        assert!(retry.available());
+        assert_eq!(retry.read(Some(&crc)), Some(false));
+        assert!(!retry.available());
+        // probed, not retried
+        assert_eq!(retry.delay(0, &crc), None);
+        assert_eq!(retry.read(Some(&crc)), None);
+        assert_eq!(retry.read(None), Some(true));
+        assert!(retry.available());
+        assert!(retry.delay(0, &crc).is_some());
+
+        // unplugged
+        let missing = SensorError::Missing("".into());
+        assert_eq!(retry.read(Some(&missing)), Some(false));
+        assert_eq!(retry.read(None), Some(true));
+    }
+}
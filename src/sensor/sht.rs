@@ -0,0 +1,259 @@
+use crc::Crc;
+use log::{debug, warn};
+use serde::*;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::sensor::{round2, I2cBus, Measure, SensorError, SensorMeasureType};
+use crate::Sensor;
+
+/// Sensirion SHT3x (SHT30/31/35) and SHT4x (SHT40/41/45) humidity sensors.
+pub struct Sht {
+    i2c: I2cBus,
+    address: u16,
+    model: ShtModel,
+    precision: ShtPrecision,
+    heater_interval: Option<Duration>,
+    heater_last: Instant,
+    measure_types: Vec<SensorMeasureType>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(default)]
+pub struct ShtConfig {
+    pub bus: u8,
+    /// 0x44, or 0x45 for SHT3x with ADDR pin high and SHT4x "B" variants
+    pub address: u16,
+    pub model: ShtModel,
+    pub precision: ShtPrecision,
+    /// Run the built-in heater after a measure when this duration has elapsed
+    /// since the last heating, disabled if not set.
+    #[serde(with = "humantime_serde")]
+    pub heater_interval: Option<Duration>,
+}
+
+impl Default for ShtConfig {
+    fn default() -> Self {
+        Self {
+            bus: 1,
+            address: Sht::I2C_ADDR,
+            model: ShtModel::default(),
+            precision: ShtPrecision::default(),
+            heater_interval: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ShtModel {
+    Sht3x,
+    #[default]
+    Sht4x,
+}
+
+/// Repeatability for SHT3x, precision for SHT4x.
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ShtPrecision {
+    #[default]
+    High,
+    Medium,
+    Low,
+}
+
+impl Sht {
+    const I2C_ADDR: u16 = 0x44;
+    const CRC: Crc<u8> = Crc::<u8>::new(&crc::CRC_8_NRSC_5);
+
+    // SHT3x heater on/off
+    const SHT3X_HEATER_ENABLE: [u8; 2] = [0x30, 0x6D];
+    const SHT3X_HEATER_DISABLE: [u8; 2] = [0x30, 0x66];
+    // SHT4x heater 200mW during 1s, then measure with high precision
+    const SHT4X_HEATER: [u8; 1] = [0x39];
+    const HEATER_DURATION: Duration = Duration::from_millis(1100);
+
+    pub fn new(config: &ShtConfig) -> Result<Self, SensorError> {
+        let i2c = I2cBus::get(config.bus)?;
+
+        let measure_types =
+            vec![SensorMeasureType::Humidity, SensorMeasureType::Temperature];
+
+        Ok(Sht {
+            i2c,
+            address: config.address,
+            model: config.model,
+            precision: config.precision,
+            heater_interval: config.heater_interval,
+            heater_last: Instant::now(),
+            measure_types,
+        })
+    }
+
+    /// Single shot measure command (without clock stretching) and its max
+    /// duration.
+    fn command(
+        model: ShtModel,
+        precision: ShtPrecision,
+    ) -> (&'static [u8], u64) {
+        match (model, precision) {
+            (ShtModel::Sht3x, ShtPrecision::High) => (&[0x24, 0x00], 16),
+            (ShtModel::Sht3x, ShtPrecision::Medium) => (&[0x24, 0x0B], 7),
+            (ShtModel::Sht3x, ShtPrecision::Low) => (&[0x24, 0x16], 5),
+            (ShtModel::Sht4x, ShtPrecision::High) => (&[0xFD], 9),
+            (ShtModel::Sht4x, ShtPrecision::Medium) => (&[0xF6], 5),
+            (ShtModel::Sht4x, ShtPrecision::Low) => (&[0xE0], 2),
+        }
+    }
+
+    fn measure_from(
+        model: ShtModel,
+        bytes: [u8; 6],
+    ) -> Result<Measure, SensorError> {
+        // each 16-bit word is followed by its CRC-8
+        for word in bytes.chunks(3) {
+            let crc = Self::CRC.checksum(word);
+            if crc != 0 {
+                return Err(SensorError::Crc(format!("0x{:02X}", crc)));
+            }
+        }
+
+        let st = u16::from_be_bytes([bytes[0], bytes[1]]) as f64 / 65535.0;
+        let srh = u16::from_be_bytes([bytes[3], bytes[4]]) as f64 / 65535.0;
+
+        let temperature = -45.0 + 175.0 * st;
+        let humidity = match model {
+            ShtModel::Sht3x => 100.0 * srh,
+            ShtModel::Sht4x => (-6.0 + 125.0 * srh).clamp(0.0, 100.0),
+        };
+
+        Ok(Measure::from([
+            (SensorMeasureType::Temperature, round2(temperature)),
+            (SensorMeasureType::Humidity, round2(humidity)),
+        ]))
+    }
+
+    fn heater(&mut self) -> Result<(), SensorError> {
+        let mut i2c = self.i2c.lock(self.address)?;
+
+        debug!("heater on");
+        match self.model {
+            ShtModel::Sht3x => {
+                i2c.write(&Self::SHT3X_HEATER_ENABLE)?;
+                thread::sleep(Self::HEATER_DURATION);
+                i2c.write(&Self::SHT3X_HEATER_DISABLE)?;
+            }
+            ShtModel::Sht4x => {
+                i2c.write(&Self::SHT4X_HEATER)?;
+                thread::sleep(Self::HEATER_DURATION);
+                // discard the measure done at the end of heating
+                let mut buffer = [0u8; 6];
+                i2c.read(&mut buffer)?;
+            }
+        }
+        self.heater_last = Instant::now();
+
+        Ok(())
+    }
+}
+
+impl Sensor for Sht {
+    fn measure(&mut self) -> Result<Measure, SensorError> {
+        let mut i2c = self.i2c.lock(self.address)?;
+
+        let (command, duration) = Self::command(self.model, self.precision);
+        i2c.write(command)?;
+
+        // wait for measure done
+        thread::sleep(Duration::from_millis(duration));
+
+        // read results
+        let mut buffer = [0u8; 6];
+        i2c.read(&mut buffer)?;
+        debug!("read: {:02X?}", buffer);
+        drop(i2c);
+
+        let measure = Sht::measure_from(self.model, buffer)?;
+
+        // heat after the measure so it is not affected
+        if let Some(interval) = self.heater_interval {
+            if self.heater_last.elapsed() >= interval {
+                // the measure is good, heating is tried again next read
+                if let Err(err) = self.heater() {
+                    warn!("Heater failed. {err}");
+                }
+            }
+        }
+
+        Ok(measure)
+    }
+
+    fn measure_types(&self) -> &Vec<SensorMeasureType> {
+        &self.measure_types
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Measure, SensorMeasureType, Sht, ShtModel};
+
+    #[test]
+    fn sht_crc_datasheet() {
+        assert_eq!(Sht::CRC.checksum(&[0xBE, 0xEF]), 0x92);
+    }
+
+    #[test]
+    fn sht3x_basic_measure() {
+        let measure = Sht::measure_from(
+            ShtModel::Sht3x,
+            [0x66, 0x66, 0x93, 0x80, 0x00, 0xA2],
+        )
+        .unwrap();
+
+        assert_eq!(
+            measure,
+            Measure::from([
+                (SensorMeasureType::Temperature, 25.0),
+                (SensorMeasureType::Humidity, 50.0)
+            ])
+        )
+    }
+
+    #[test]
+    fn sht4x_basic_measure() {
+        let measure = Sht::measure_from(
+            ShtModel::Sht4x,
+            [0x66, 0x66, 0x93, 0x72, 0xB0, 0xDC],
+        )
+        .unwrap();
+
+        assert_eq!(
+            measure,
+            Measure::from([
+                (SensorMeasureType::Temperature, 25.0),
+                (SensorMeasureType::Humidity, 50.0)
+            ])
+        )
+    }
+
+    #[test]
+    fn sht4x_humidity_clamped() {
+        let measure = Sht::measure_from(
+            ShtModel::Sht4x,
+            [0x66, 0x66, 0x93, 0xFF, 0xFF, 0xAC],
+        )
+        .unwrap();
+
+        assert_eq!(measure.get(&SensorMeasureType::Humidity), Some(100.0))
+    }
+
+    #[test]
+    fn sht_bad_crc() {
+        // temperature word ok, humidity CRC wrong
+        assert!(Sht::measure_from(
+            ShtModel::Sht4x,
+            [0x66, 0x66, 0x93, 0x72, 0xB0, 0x00],
+        )
+        .is_err())
+    }
+}
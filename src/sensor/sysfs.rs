@@ -0,0 +1,273 @@
+use std::fs;
+
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::{Measure, Sensor, SensorError, SensorMeasureType};
+
+/// Channels exposed by kernel drivers, hwmon `*_input` or IIO `in_*_raw`.
+pub struct Sysfs {
+    measure_types: Vec<SensorMeasureType>,
+    channels: Vec<SysfsChannel>,
+}
+
+struct SysfsChannel {
+    measure_type: SensorMeasureType,
+    path: String,
+    scale: f64,
+    offset: f64,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(default)]
+pub struct SysfsConfig {
+    pub channels: Vec<SysfsChannelConfig>,
+}
+
+/// Value published is `(raw + offset) * scale`, when not set `scale` and
+/// `offset` are read from the IIO `_scale` and `_offset` attributes (and the
+/// IIO unit converted). Without them an IIO channel is converted from the IIO
+/// unit and a hwmon `_input` one from its milli-units.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct SysfsChannelConfig {
+    pub path: String,
+    pub measure: SensorMeasureType,
+    pub scale: Option<f64>,
+    pub offset: Option<f64>,
+}
+
+impl Sysfs {
+    pub fn new(config: &SysfsConfig) -> Result<Self, SensorError> {
+        for (i, channel) in config.channels.iter().enumerate() {
+            if config.channels[..i]
+                .iter()
+                .any(|c| c.measure == channel.measure)
+            {
+                return Err(SensorError::Config(format!(
+                    "{} measured by many channels",
+                    channel.measure.key()
+                )));
+            }
+        }
+
+        let channels = config
+            .channels
+            .iter()
+            .map(SysfsChannel::new)
+            .collect::<Result<Vec<_>, _>>()?;
+        let measure_types = channels.iter().map(|c| c.measure_type).collect();
+
+        Ok(Self {
+            measure_types,
+            channels,
+        })
+    }
+
+    fn measure_from(
+        string: &str,
+        scale: f64,
+        offset: f64,
+    ) -> Result<f32, SensorError> {
+        let raw = string.trim().parse::<f64>()?;
+
+        Ok(((raw + offset) * scale) as f32)
+    }
+
+    /// Files where the IIO `attribute` of the channel at `path` can be found,
+    /// specific to the channel first then shared by its type:
+    /// `in_voltage0_raw` => `in_voltage0_scale`, `in_voltage_scale`
+    fn attribute_paths(path: &str, attribute: &str) -> Vec<String> {
+        let Some(channel) = path.strip_suffix("_raw") else {
+            return vec![];
+        };
+        let shared = channel.trim_end_matches(|c: char| c.is_ascii_digit());
+
+        let mut paths = vec![format!("{channel}_{attribute}")];
+        if shared != channel {
+            paths.push(format!("{shared}_{attribute}"));
+        }
+        paths
+    }
+
+    /// IIO channels are `in_*` files, hwmon ones `temp1_input`, `in0_input`...
+    fn is_iio(path: &str) -> bool {
+        let file = path.rsplit('/').next().unwrap_or(path);
+        file.starts_with("in_") || path.contains("iio:device")
+    }
+
+    /// IIO values are in m°C, m%RH, kPa, mV and mW
+    fn iio_unit(measure_type: &SensorMeasureType) -> f64 {
+        match measure_type {
+            SensorMeasureType::Temperature
+            | SensorMeasureType::CpuTemperature
+            | SensorMeasureType::Humidity
+            | SensorMeasureType::Voltage
+            | SensorMeasureType::Power => 0.001,
+            SensorMeasureType::Pressure => 10.0,
+            _ => 1.0,
+        }
+    }
+
+    /// hwmon `_input` values are in m°C, m%RH, mV and µW
+    fn hwmon_unit(measure_type: &SensorMeasureType) -> f64 {
+        match measure_type {
+            SensorMeasureType::Power => 0.000001,
+            _ => 0.001,
+        }
+    }
+
+    /// Scale of a channel without `_scale` attribute
+    fn unit(path: &str, measure_type: &SensorMeasureType) -> f64 {
+        if Sysfs::is_iio(path) {
+            Sysfs::iio_unit(measure_type)
+        } else if path.ends_with("_input") {
+            Sysfs::hwmon_unit(measure_type)
+        } else {
+            1.0
+        }
+    }
+
+    fn read_attribute(
+        path: &str,
+        attribute: &str,
+    ) -> Result<Option<f64>, SensorError> {
+        for path in Self::attribute_paths(path, attribute) {
+            if let Ok(string) = fs::read_to_string(&path) {
+                debug!("{path}: {string:?}");
+                return Ok(Some(string.trim().parse::<f64>()?));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl SysfsChannel {
+    fn new(config: &SysfsChannelConfig) -> Result<Self, SensorError> {
+        let _ = fs::metadata(&config.path)?;
+
+        let scale = match config.scale {
+            Some(scale) => scale,
+            None => match Sysfs::read_attribute(&config.path, "scale")? {
+                Some(scale) => scale * Sysfs::iio_unit(&config.measure),
+                None => Sysfs::unit(&config.path, &config.measure),
+            },
+        };
+        let offset = match config.offset {
+            Some(offset) => offset,
+            None => Sysfs::read_attribute(&config.path, "offset")?
+                .unwrap_or_default(),
+        };
+
+        Ok(Self {
+            measure_type: config.measure,
+            path: config.path.clone(),
+            scale,
+            offset,
+        })
+    }
+}
+
+impl Sensor for Sysfs {
+    fn measure(&mut self) -> Result<Measure, SensorError> {
+        let mut measure = Measure::default();
+
+        for channel in &self.channels {
+            let string = fs::read_to_string(&channel.path)?;
+            debug!("{}: {string:?}", channel.path);
+
+            let value =
+                Sysfs::measure_from(&string, channel.scale, channel.offset)?;
+            measure.set(&channel.measure_type, value);
+        }
+
+        Ok(measure)
+    }
+
+    fn measure_types(&self) -> &Vec<SensorMeasureType> {
+        &self.measure_types
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{SensorError, SensorMeasureType};
+
+    use super::{Sysfs, SysfsChannelConfig, SysfsConfig};
+
+    #[test]
+    fn sysfs_hwmon_measure() {
+        assert_eq!(Sysfs::measure_from("23125\n", 0.001, 0.0).unwrap(), 23.125)
+    }
+
+    #[test]
+    fn sysfs_iio_measure() {
+        // in_temp_raw with in_temp_scale and in_temp_offset
+        assert_eq!(
+            Sysfs::measure_from("1200\n", 10.0, -200.0).unwrap(),
+            10000.0
+        );
+        assert!(Sysfs::measure_from("\n", 1.0, 0.0).is_err());
+    }
+
+    #[test]
+    fn sysfs_units() {
+        let iio = |t| Sysfs::iio_unit(&t);
+        assert_eq!(iio(SensorMeasureType::Temperature), 0.001);
+        assert_eq!(iio(SensorMeasureType::Pressure), 10.0);
+        assert_eq!(iio(SensorMeasureType::Voltage), 0.001);
+        assert_eq!(iio(SensorMeasureType::Power), 0.001);
+
+        let hwmon = |t| Sysfs::hwmon_unit(&t);
+        assert_eq!(hwmon(SensorMeasureType::Temperature), 0.001);
+        assert_eq!(hwmon(SensorMeasureType::Voltage), 0.001);
+        assert_eq!(hwmon(SensorMeasureType::Power), 0.000001);
+    }
+
+    #[test]
+    fn sysfs_iio_input() {
+        let unit = |path| Sysfs::unit(path, &SensorMeasureType::Pressure);
+        // kPa => hPa
+        assert_eq!(unit("iio:device0/in_pressure_input"), 10.0);
+        assert_eq!(unit("bmp280/in_pressure_input"), 10.0);
+        assert_eq!(
+            Sysfs::unit("hwmon0/in0_input", &SensorMeasureType::Voltage),
+            0.001
+        );
+        assert_eq!(unit("pressure"), 1.0);
+    }
+
+    #[test]
+    fn sysfs_channels_config() {
+        let channel = |path: &str| SysfsChannelConfig {
+            path: path.into(),
+            measure: SensorMeasureType::Temperature,
+            scale: None,
+            offset: None,
+        };
+
+        assert!(matches!(
+            Sysfs::new(&SysfsConfig {
+                channels: vec![channel("temp1_input"), channel("temp2_input")],
+            }),
+            Err(SensorError::Config(_))
+        ));
+    }
+
+    #[test]
+    fn sysfs_attribute_paths() {
+        assert_eq!(
+            Sysfs::attribute_paths("iio:device0/in_voltage0_raw", "scale"),
+            vec![
+                "iio:device0/in_voltage0_scale".to_string(),
+                "iio:device0/in_voltage_scale".to_string()
+            ]
+        );
+        assert_eq!(
+            Sysfs::attribute_paths("iio:device0/in_temp_raw", "offset"),
+            vec!["iio:device0/in_temp_offset".to_string()]
+        );
+        assert!(
+            Sysfs::attribute_paths("hwmon0/temp1_input", "scale").is_empty()
+        );
+    }
+}
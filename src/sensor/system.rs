@@ -0,0 +1,351 @@
+use std::ffi::CString;
+use std::fs;
+use std::mem::MaybeUninit;
+
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::sensor::round2;
+use crate::{Measure, NamedSensors, Sensor, SensorError, SensorMeasureType};
+
+/// Host metrics: load average, memory, uptime and network counters.
+pub struct System {
+    measure_types: Vec<SensorMeasureType>,
+    interfaces: Vec<String>,
+}
+
+/// Usage of the filesystem mounted at a mount point.
+pub struct SystemDisk {
+    measure_types: Vec<SensorMeasureType>,
+    mount_point: CString,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(default)]
+pub struct SystemConfig {
+    /// Each mount point is published as its own `<id>-disk-<mount>` sensor
+    pub mount_points: Vec<String>,
+    /// Interfaces counted in network measures, all but `lo` if empty
+    pub interfaces: Vec<String>,
+}
+
+impl Default for SystemConfig {
+    fn default() -> Self {
+        Self {
+            mount_points: vec!["/".into()],
+            interfaces: vec![],
+        }
+    }
+}
+
+impl System {
+    pub fn new(config: &SystemConfig) -> Self {
+        let measure_types = vec![
+            SensorMeasureType::Load1m,
+            SensorMeasureType::Load5m,
+            SensorMeasureType::Load15m,
+            SensorMeasureType::MemoryUsage,
+            SensorMeasureType::Uptime,
+            SensorMeasureType::NetworkReceived,
+            SensorMeasureType::NetworkTransmitted,
+        ];
+
+        Self {
+            measure_types,
+            interfaces: config.interfaces.clone(),
+        }
+    }
+
+    /// The host sensor followed by a disk sensor for each mount point.
+    pub fn new_all(
+        sensor_id: &str,
+        config: &SystemConfig,
+    ) -> Result<NamedSensors, SensorError> {
+        let mut sensors: Vec<Box<dyn Sensor>> =
+            vec![Box::new(System::new(config))];
+        for mount_point in &config.mount_points {
+            sensors.push(Box::new(SystemDisk::new(mount_point)?));
+        }
+
+        Ok(Self::sensor_ids(sensor_id, config)
+            .into_iter()
+            .zip(sensors)
+            .collect())
+    }
+
+    /// Sensor ids of the sensors `new_all` creates
+    pub fn sensor_ids(sensor_id: &str, config: &SystemConfig) -> Vec<String> {
+        std::iter::once(sensor_id.into())
+            .chain(
+                config
+                    .mount_points
+                    .iter()
+                    .map(|m| SystemDisk::sensor_id(sensor_id, m)),
+            )
+            .collect()
+    }
+
+    /// `(load 1m, load 5m, load 15m)` from `/proc/loadavg`
+    fn load_from(string: &str) -> Result<(f32, f32, f32), SensorError> {
+        let mut loads = string.split_whitespace().map(str::parse::<f32>);
+        match (loads.next(), loads.next(), loads.next()) {
+            (Some(l1), Some(l5), Some(l15)) => Ok((l1?, l5?, l15?)),
+            _ => {
+                Err(SensorError::Parse(format!("Invalid loadavg: {string:?}")))
+            }
+        }
+    }
+
+    /// Used memory in % from `/proc/meminfo`
+    fn memory_usage_from(string: &str) -> Result<f32, SensorError> {
+        let field = |name: &str| -> Result<f64, SensorError> {
+            let line = string
+                .lines()
+                .find(|l| l.starts_with(name))
+                .ok_or_else(|| {
+                    SensorError::Parse(format!("{name} not found in meminfo"))
+                })?;
+            let value = line.split_whitespace().nth(1).ok_or_else(|| {
+                SensorError::Parse(format!("Invalid meminfo line: {line:?}"))
+            })?;
+            Ok(value.parse::<f64>()?)
+        };
+
+        let total = field("MemTotal:")?;
+        let available = field("MemAvailable:")?;
+        let usage = (total - available) / total * 100.0;
+
+        Ok(round2(usage))
+    }
+
+    /// Uptime in hours from `/proc/uptime`, its 2 decimals kept by an `f32`
+    /// for 7 years where seconds would be rounded after 6 months
+    fn uptime_from(string: &str) -> Result<f32, SensorError> {
+        let uptime = string
+            .split_whitespace()
+            .next()
+            .ok_or_else(|| {
+                SensorError::Parse(format!("Invalid uptime: {string:?}"))
+            })?
+            .parse::<f64>()?;
+
+        Ok(round2(uptime / 3600.0))
+    }
+
+    /// `(received, transmitted)` in GiB summed over `interfaces` from
+    /// `/proc/net/dev`, 2 decimals kept by an `f32` up to 64 TiB
+    fn network_from(
+        string: &str,
+        interfaces: &[String],
+    ) -> Result<(f32, f32), SensorError> {
+        let mut received = 0u64;
+        let mut transmitted = 0u64;
+
+        // skip the 2 header lines
+        for line in string.lines().skip(2) {
+            let Some((interface, counters)) = line.split_once(':') else {
+                continue;
+            };
+            let interface = interface.trim();
+            let counted = if interfaces.is_empty() {
+                interface != "lo"
+            } else {
+                interfaces.iter().any(|i| i == interface)
+            };
+            if !counted {
+                continue;
+            }
+
+            let counters = counters.split_whitespace().collect::<Vec<_>>();
+            match (counters.first(), counters.get(8)) {
+                (Some(rx), Some(tx)) => {
+                    received += rx.parse::<u64>()?;
+                    transmitted += tx.parse::<u64>()?;
+                }
+                _ => {
+                    return Err(SensorError::Parse(format!(
+                        "Invalid net/dev: {line:?}"
+                    )))
+                }
+            }
+        }
+
+        let gib = |bytes: u64| round2(bytes as f64 / 1073741824.0);
+        Ok((gib(received), gib(transmitted)))
+    }
+}
+
+impl Sensor for System {
+    fn measure(&mut self) -> Result<Measure, SensorError> {
+        let loadavg = fs::read_to_string("/proc/loadavg")?;
+        let meminfo = fs::read_to_string("/proc/meminfo")?;
+        let uptime = fs::read_to_string("/proc/uptime")?;
+        let net_dev = fs::read_to_string("/proc/net/dev")?;
+        debug!("loadavg: {loadavg:?}, uptime: {uptime:?}");
+
+        let (load_1m, load_5m, load_15m) = System::load_from(&loadavg)?;
+        let (received, transmitted) =
+            System::network_from(&net_dev, &self.interfaces)?;
+
+        Ok(Measure::from([
+            (SensorMeasureType::Load1m, load_1m),
+            (SensorMeasureType::Load5m, load_5m),
+            (SensorMeasureType::Load15m, load_15m),
+            (
+                SensorMeasureType::MemoryUsage,
+                System::memory_usage_from(&meminfo)?,
+            ),
+            (SensorMeasureType::Uptime, System::uptime_from(&uptime)?),
+            (SensorMeasureType::NetworkReceived, received),
+            (SensorMeasureType::NetworkTransmitted, transmitted),
+        ]))
+    }
+
+    fn measure_types(&self) -> &Vec<SensorMeasureType> {
+        &self.measure_types
+    }
+}
+
+impl SystemDisk {
+    /// A mount point that can't be read now won't be later
+    pub fn new(mount_point: &str) -> Result<Self, SensorError> {
+        let invalid = |e: &dyn std::fmt::Display| {
+            SensorError::Config(format!("{mount_point}: {e}"))
+        };
+        let disk = Self {
+            measure_types: vec![SensorMeasureType::DiskUsage],
+            mount_point: CString::new(mount_point).map_err(|e| invalid(&e))?,
+        };
+        disk.statvfs().map_err(|e| invalid(&e))?;
+
+        Ok(disk)
+    }
+
+    fn statvfs(&self) -> Result<libc::statvfs, SensorError> {
+        let mut stat = MaybeUninit::<libc::statvfs>::uninit();
+        // SAFETY: `mount_point` is a valid C string and `stat` is only read
+        // when the call succeeded.
+        unsafe {
+            if libc::statvfs(self.mount_point.as_ptr(), stat.as_mut_ptr()) != 0
+            {
+                return Err(std::io::Error::last_os_error().into());
+            }
+            Ok(stat.assume_init())
+        }
+    }
+
+    /// `/` => `<id>-disk-root`, `/mnt/data` => `<id>-disk-mnt-data`
+    fn sensor_id(sensor_id: &str, mount_point: &str) -> String {
+        let name = mount_point.trim_matches('/').replace('/', "-");
+        let name = if name.is_empty() { "root".into() } else { name };
+
+        format!("{sensor_id}-disk-{name}")
+    }
+
+    /// Used space in % as `df` computes it, space reserved to root excluded
+    /// (block counts are only 32 bits on some targets)
+    fn disk_usage_from(
+        blocks: impl Into<u64>,
+        free: impl Into<u64>,
+        available: impl Into<u64>,
+    ) -> f32 {
+        let (blocks, free, available) =
+            (blocks.into(), free.into(), available.into());
+        let used = blocks - free;
+        let usage = match used + available {
+            0 => 0.0,
+            total => used as f64 / total as f64 * 100.0,
+        };
+
+        round2(usage)
+    }
+}
+
+impl Sensor for SystemDisk {
+    fn measure(&mut self) -> Result<Measure, SensorError> {
+        let stat = self.statvfs()?;
+
+        Ok(Measure::from([(
+            SensorMeasureType::DiskUsage,
+            SystemDisk::disk_usage_from(
+                stat.f_blocks,
+                stat.f_bfree,
+                stat.f_bavail,
+            ),
+        )]))
+    }
+
+    fn measure_types(&self) -> &Vec<SensorMeasureType> {
+        &self.measure_types
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::SensorError;
+
+    use super::{System, SystemDisk};
+
+    #[test]
+    fn system_load() {
+        assert_eq!(
+            System::load_from("0.26 0.32 0.22 2/72 9809\n").unwrap(),
+            (0.26, 0.32, 0.22)
+        );
+        assert!(System::load_from("0.26\n").is_err());
+    }
+
+    #[test]
+    fn system_memory_usage() {
+        let meminfo = "MemTotal:        1000000 kB\n\
+                       MemFree:          200000 kB\n\
+                       MemAvailable:     750000 kB\n";
+
+        assert_eq!(System::memory_usage_from(meminfo).unwrap(), 25.0);
+        assert!(System::memory_usage_from("MemTotal: 1000 kB\n").is_err());
+    }
+
+    #[test]
+    fn system_uptime() {
+        assert_eq!(System::uptime_from("5400.14 1023.59\n").unwrap(), 1.5);
+        // 200 days
+        assert_eq!(
+            System::uptime_from("17280037.5 1023.59\n").unwrap(),
+            4800.01
+        );
+    }
+
+    #[test]
+    fn system_network() {
+        let net_dev = "\
+Inter-|   Receive                                                |  Transmit
+ face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed
+    lo: 16823191    2332    0    0    0     0          0         0 16823191    2332    0    0    0     0       0          0
+  eth0: 1073741824    1000    0    0    0     0          0         0  2147483648     900    0    0    0     0       0          0
+ wlan0: 536870912     500    0    0    0     0          0         0   536870912     400    0    0    0     0       0          0
+";
+
+        assert_eq!(System::network_from(net_dev, &[]).unwrap(), (1.5, 2.5));
+        assert_eq!(
+            System::network_from(net_dev, &["eth0".into()]).unwrap(),
+            (1.0, 2.0)
+        );
+    }
+
+    #[test]
+    fn system_disk() {
+        assert!(SystemDisk::new("/").is_ok());
+        assert!(matches!(
+            SystemDisk::new("/not/mounted"),
+            Err(SensorError::Config(_))
+        ));
+        assert_eq!(SystemDisk::sensor_id("host", "/"), "host-disk-root");
+        assert_eq!(
+            SystemDisk::sensor_id("host", "/mnt/data/"),
+            "host-disk-mnt-data"
+        );
+        // 100 blocks, 40 free of which 10 reserved to root
+        assert_eq!(SystemDisk::disk_usage_from(100u64, 40u64, 30u64), 66.67);
+        assert_eq!(SystemDisk::disk_usage_from(0u64, 0u64, 0u64), 0.0);
+    }
+}
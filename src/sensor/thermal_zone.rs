@@ -0,0 +1,164 @@
+use log::{debug, warn};
+use serde::*;
+use std::fs;
+use std::process::Command;
+
+use crate::sensor::{Measure, SensorError, SensorMeasureType};
+use crate::Sensor;
+
+/// SoC temperature from a kernel thermal zone, and optionally the Raspberry
+/// Pi firmware throttling state.
+pub struct ThermalZone {
+    path: String,
+    throttled: bool,
+    measure_types: Vec<SensorMeasureType>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(default)]
+pub struct ThermalZoneConfig {
+    /// `/sys/class/thermal/thermal_zone<zone>`
+    pub zone: u8,
+    /// Also report the under-voltage and throttling flags, their current
+    /// state only and not the "has occurred" ones
+    pub throttled: bool,
+}
+
+impl ThermalZone {
+    const THROTTLED_PATH: &'static str =
+        "/sys/devices/platform/soc/soc:firmware/get_throttled";
+
+    // get_throttled bits of the current state, bits 16 to 19 tell the same
+    // has occurred since boot
+    const UNDER_VOLTAGE: u32 = 1 << 0;
+    const THROTTLED: u32 = 1 << 2;
+
+    pub fn new(config: &ThermalZoneConfig) -> Result<Self, SensorError> {
+        let path =
+            format!("/sys/class/thermal/thermal_zone{}/temp", config.zone);
+
+        let _ = fs::metadata(&path)?;
+
+        let mut measure_types = vec![SensorMeasureType::CpuTemperature];
+        if config.throttled {
+            measure_types.push(SensorMeasureType::UnderVoltage);
+            measure_types.push(SensorMeasureType::Throttled);
+        }
+
+        Ok(Self {
+            path,
+            throttled: config.throttled,
+            measure_types,
+        })
+    }
+
+    /// The firmware value from sysfs if exposed by the kernel, `vcgencmd`
+    /// otherwise.
+    fn read_throttled() -> Result<String, SensorError> {
+        match fs::read_to_string(Self::THROTTLED_PATH) {
+            Ok(string) => Ok(string),
+            Err(_) => {
+                let output =
+                    Command::new("vcgencmd").arg("get_throttled").output()?;
+                String::from_utf8(output.stdout)
+                    .map_err(|e| SensorError::Parse(e.to_string()))
+            }
+        }
+    }
+
+    /// Parse `throttled=0x50005` (vcgencmd) or `50005` (sysfs).
+    fn throttled_from(string: &str) -> Result<u32, SensorError> {
+        let string = string.trim();
+        let string = string.strip_prefix("throttled=").unwrap_or(string);
+        let string = string.strip_prefix("0x").unwrap_or(string);
+
+        Ok(u32::from_str_radix(string, 16)?)
+    }
+
+    fn measure_from(
+        temp: &str,
+        throttled: Option<u32>,
+    ) -> Result<Measure, SensorError> {
+        let temperature = temp.trim().parse::<isize>()? as f64 * 0.001;
+
+        let mut measure = Measure::from([(
+            SensorMeasureType::CpuTemperature,
+            temperature as f32,
+        )]);
+        if let Some(throttled) = throttled {
+            let flag = |bit: u32| (throttled & bit != 0) as u8 as f32;
+            measure.set(
+                &SensorMeasureType::UnderVoltage,
+                flag(Self::UNDER_VOLTAGE),
+            );
+            measure.set(&SensorMeasureType::Throttled, flag(Self::THROTTLED));
+        }
+
+        Ok(measure)
+    }
+}
+
+impl Sensor for ThermalZone {
+    fn measure(&mut self) -> Result<Measure, SensorError> {
+        let temp = fs::read_to_string(&self.path)?;
+        debug!("temp: {temp:?}");
+
+        let throttled = match self.throttled {
+            true => match Self::read_throttled().and_then(|string| {
+                debug!("throttled: {string:?}");
+                Self::throttled_from(&string)
+            }) {
+                Ok(throttled) => Some(throttled),
+                // the temperature is still worth publishing
+                Err(err) => {
+                    warn!("Throttled state not read. {err}");
+                    None
+                }
+            },
+            false => None,
+        };
+
+        ThermalZone::measure_from(&temp, throttled)
+    }
+
+    fn measure_types(&self) -> &Vec<SensorMeasureType> {
+        &self.measure_types
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Measure, SensorMeasureType};
+
+    use super::ThermalZone;
+
+    #[test]
+    fn thermal_zone_basic_measure() {
+        let measure = ThermalZone::measure_from("48312\n", None).unwrap();
+
+        assert_eq!(
+            measure,
+            Measure::from([(SensorMeasureType::CpuTemperature, 48.312)])
+        )
+    }
+
+    #[test]
+    fn thermal_zone_throttled() {
+        assert_eq!(
+            ThermalZone::throttled_from("throttled=0x50005\n").unwrap(),
+            0x50005
+        );
+        assert_eq!(ThermalZone::throttled_from("50000\n").unwrap(), 0x50000);
+        assert!(ThermalZone::throttled_from("throttled=?").is_err());
+
+        let measure =
+            ThermalZone::measure_from("48312\n", Some(0x50005)).unwrap();
+        assert_eq!(measure.get(&SensorMeasureType::UnderVoltage), Some(1.0));
+        assert_eq!(measure.get(&SensorMeasureType::Throttled), Some(1.0));
+
+        let measure =
+            ThermalZone::measure_from("48312\n", Some(0x50000)).unwrap();
+        assert_eq!(measure.get(&SensorMeasureType::UnderVoltage), Some(0.0));
+        assert_eq!(measure.get(&SensorMeasureType::Throttled), Some(0.0));
+    }
+}